@@ -5,13 +5,60 @@
 // file size, and processing time. It also tracks any errors that occur
 // during processing.
 
+use cpu_time::ThreadTime;
+use flate2::read::GzDecoder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader, Cursor, Read, Seek, SeekFrom};
 use std::time::{Duration, Instant};
 
+/// Aggregated counts of characters by Unicode class, computed alongside
+/// `FileStats::char_frequencies` as a compact, stable summary that's
+/// cheap to print (unlike the full per-character map). Punctuation is
+/// detected via `char::is_ascii_punctuation`, so non-ASCII punctuation is
+/// counted under `other` rather than `punctuation`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CharClassCounts {
+    pub letters: usize,
+    pub digits: usize,
+    pub whitespace: usize,
+    pub punctuation: usize,
+    pub other: usize,
+}
+
+impl CharClassCounts {
+    /// Classifies `ch` into exactly one of the five counted classes and
+    /// increments it.
+    fn record(&mut self, ch: char) {
+        if ch.is_alphabetic() {
+            self.letters += 1;
+        } else if ch.is_numeric() {
+            self.digits += 1;
+        } else if ch.is_whitespace() {
+            self.whitespace += 1;
+        } else if ch.is_ascii_punctuation() {
+            self.punctuation += 1;
+        } else {
+            self.other += 1;
+        }
+    }
+
+    /// Adds `other`'s counts into `self`, for combining per-chunk class
+    /// counts into a single aggregate.
+    fn merge(&mut self, other: &CharClassCounts) {
+        self.letters += other.letters;
+        self.digits += other.digits;
+        self.whitespace += other.whitespace;
+        self.punctuation += other.punctuation;
+        self.other += other.other;
+    }
+}
+
 /// Holds statistical information about a single file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileStats {
     /// Total number of words in the file
     pub word_count: usize,
@@ -22,8 +69,135 @@ pub struct FileStats {
     /// Frequency count of each character in the file
     pub char_frequencies: HashMap<char, usize>,
 
-    /// File size in bytes
+    /// Aggregated counts of characters by Unicode class (letters, digits,
+    /// whitespace, punctuation, other).
+    pub char_classes: CharClassCounts,
+
+    /// File size in bytes, as stored on disk (compressed size for `.gz` inputs)
     pub size_bytes: u64,
+
+    /// MIME type (e.g. `"image/png"`, `"application/gzip"`, `"text/plain"`)
+    /// detected from the file's leading magic bytes via the `infer` crate,
+    /// independent of its extension. Falls back to `"text/plain"` for
+    /// valid UTF-8 content `infer` doesn't recognize, and
+    /// `"application/octet-stream"` when neither applies.
+    pub detected_type: String,
+
+    /// Size of the content actually analyzed, in bytes, after decompression.
+    /// `None` when the file was not gzip-compressed (equal to `size_bytes` otherwise).
+    pub decompressed_size_bytes: Option<u64>,
+
+    /// The highest-frequency word in the file (case-insensitive, punctuation
+    /// trimmed) and its occurrence count. Ties are broken lexicographically.
+    /// Only populated when `AnalyzeOptions::track_word_frequencies` is set,
+    /// since the underlying word-frequency map is memory-heavy for large files.
+    pub most_common_word: Option<(String, usize)>,
+
+    /// Per-word occurrence counts for the file (case-insensitive, punctuation
+    /// trimmed), for callers that need more than just the single most common
+    /// word, e.g. `--top-words` aggregating across a whole corpus. Populated
+    /// under the same `AnalyzeOptions::track_word_frequencies` gate as
+    /// `most_common_word`, and empty otherwise.
+    pub word_frequencies: HashMap<String, usize>,
+
+    /// The line-ending style detected while reading the file as UTF-8 text.
+    pub line_ending: LineEnding,
+
+    /// The indentation style detected from lines' leading whitespace while
+    /// reading the file as UTF-8 text.
+    pub indentation: Indentation,
+
+    /// True when both tab-indented and space-indented non-blank lines
+    /// appear in the file (i.e. `indentation == Indentation::Mixed`), or
+    /// when a single line's leading whitespace itself contains both a tab
+    /// and a space, even if every other line is consistent. Lets linters
+    /// flag indentation mixing without having to special-case the
+    /// single-line case on top of `indentation`.
+    pub has_mixed_indentation: bool,
+
+    /// Number of lines matching the `--match` regex, if one was supplied.
+    pub regex_match_count: Option<usize>,
+
+    /// The matching lines themselves (1-based line number, trimmed text),
+    /// collected only when `--match` is active, for grep-like display.
+    pub regex_matches: Option<Vec<(usize, String)>>,
+
+    /// True when the file is zero bytes, so empty files can be
+    /// distinguished from tiny-but-nonempty ones in the summary.
+    pub is_empty: bool,
+
+    /// True when `AnalyzeOptions::max_read_bytes` cut analysis short, so
+    /// counts reflect only a sampled prefix of the file rather than its
+    /// full contents.
+    pub truncated: bool,
+
+    /// Number of distinct non-blank lines (trailing newline trimmed
+    /// before comparison), for log deduplication use-cases. Only
+    /// populated when `AnalyzeOptions::track_unique_lines` is set, since
+    /// the underlying `HashSet<String>` is memory-heavy for large files.
+    pub unique_line_count: Option<usize>,
+
+    /// Number of lines (terminator excluded) that end in a space or tab,
+    /// for flagging trailing-whitespace style violations.
+    pub lines_with_trailing_ws: usize,
+
+    /// Length, in characters, of the longest line (terminator excluded).
+    pub longest_line_len: usize,
+
+    /// 1-based line number of `longest_line_len`. On ties, the first
+    /// occurrence wins.
+    pub longest_line_no: usize,
+
+    /// True when UTF-8 decoding failed and the file was counted via the
+    /// raw-byte fallback path instead of the normal text line loop, so
+    /// word/line counts reflect byte-level heuristics rather than real
+    /// text semantics.
+    pub fell_back_to_binary: bool,
+
+    /// Number of distinct invalid UTF-8 byte spans found while scanning
+    /// the raw-byte fallback path, for data-quality audits that want to
+    /// know how corrupted a file is rather than just that it isn't valid
+    /// UTF-8. A contiguous run of malformed bytes counts as one sequence.
+    /// Always `0` when `fell_back_to_binary` is `false`.
+    pub invalid_utf8_sequences: usize,
+
+    /// Number of U+FFFD replacement characters substituted for invalid
+    /// UTF-8 byte sequences when `AnalyzeOptions::lossy_utf8` handled a
+    /// file that isn't valid UTF-8, rather than dropping to the raw-byte
+    /// fallback. Always `0` when `lossy_utf8` was off or the file decoded
+    /// cleanly.
+    pub replacement_chars: usize,
+
+    /// True when the file began with a UTF-8 byte-order mark (`EF BB BF`).
+    /// The BOM is stripped before analysis, so it never appears in
+    /// `char_frequencies` or contributes to `longest_line_len`.
+    pub had_bom: bool,
+
+    /// SHA-256 digest of the file's raw on-disk bytes, as a lowercase hex
+    /// string. Only populated when `AnalyzeOptions::track_content_hash` is
+    /// set, since hashing requires a full extra read of the file.
+    pub content_hash: Option<String>,
+
+    /// Number of bytes actually consumed by the analysis read loop, as
+    /// opposed to `size_bytes` (read from file metadata before the read
+    /// starts). A file that grows or shrinks between the two points
+    /// (common for an actively-written log) makes the two disagree;
+    /// callers that need an accurate running total (e.g.
+    /// `total_bytes_processed`) should sum this field instead of
+    /// `size_bytes`.
+    pub bytes_read: u64,
+
+    /// Best-effort guess at the file's programming language, from its
+    /// extension (a built-in mapping) or, for extensionless files, a
+    /// shebang-line sniff (`#!/usr/bin/env python` -> `"Python"`). `None`
+    /// when neither yields a match.
+    pub language: Option<String>,
+}
+
+impl Default for FileStats {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FileStats {
@@ -33,15 +207,306 @@ impl FileStats {
             word_count: 0,
             line_count: 0,
             char_frequencies: HashMap::new(),
+            char_classes: CharClassCounts::default(),
             size_bytes: 0,
+            detected_type: String::new(),
+            decompressed_size_bytes: None,
+            most_common_word: None,
+            word_frequencies: HashMap::new(),
+            line_ending: LineEnding::Unknown,
+            indentation: Indentation::None,
+            has_mixed_indentation: false,
+            regex_match_count: None,
+            regex_matches: None,
+            is_empty: false,
+            truncated: false,
+            unique_line_count: None,
+            lines_with_trailing_ws: 0,
+            longest_line_len: 0,
+            longest_line_no: 0,
+            fell_back_to_binary: false,
+            invalid_utf8_sequences: 0,
+            replacement_chars: 0,
+            had_bom: false,
+            content_hash: None,
+            bytes_read: 0,
+            language: None,
+        }
+    }
+
+    /// Folds `other` into `self`, combining two `FileStats` computed over
+    /// disjoint pieces of data (e.g. chunks of one file, or files within a
+    /// directory) into a single aggregate. Counts and sizes are summed,
+    /// `char_frequencies` is unioned, and fields that only make sense for a
+    /// single contiguous region (`most_common_word`, `line_ending`,
+    /// `indentation`) are combined as best as can be inferred from the two
+    /// summaries alone.
+    #[allow(dead_code)]
+    pub fn merge(&mut self, other: &FileStats) {
+        self.word_count += other.word_count;
+        self.line_count += other.line_count;
+        self.lines_with_trailing_ws += other.lines_with_trailing_ws;
+        self.size_bytes += other.size_bytes;
+        self.bytes_read += other.bytes_read;
+        self.is_empty = self.is_empty && other.is_empty;
+
+        for (&ch, &count) in &other.char_frequencies {
+            *self.char_frequencies.entry(ch).or_insert(0) += count;
+        }
+        self.char_classes.merge(&other.char_classes);
+
+        // `self` is treated as occurring before `other`, so only a strictly
+        // longer line in `other` overrides, preserving first-occurrence on
+        // ties the same way a single contiguous pass would.
+        if other.longest_line_len > self.longest_line_len {
+            self.longest_line_len = other.longest_line_len;
+            self.longest_line_no = other.longest_line_no;
+        }
+
+        if self.detected_type.is_empty() {
+            self.detected_type = other.detected_type.clone();
+        }
+
+        self.decompressed_size_bytes = match (self.decompressed_size_bytes, other.decompressed_size_bytes) {
+            (Some(a), Some(b)) => Some(a + b),
+            (Some(a), None) => Some(a),
+            (None, Some(b)) => Some(b),
+            (None, None) => None,
+        };
+
+        self.most_common_word = match (&self.most_common_word, &other.most_common_word) {
+            (Some((word_a, count_a)), Some((word_b, count_b))) => {
+                if count_b > count_a || (count_b == count_a && word_b < word_a) {
+                    Some((word_b.clone(), *count_b))
+                } else {
+                    Some((word_a.clone(), *count_a))
+                }
+            }
+            (Some(a), None) => Some(a.clone()),
+            (None, Some(b)) => Some(b.clone()),
+            (None, None) => None,
+        };
+
+        for (word, count) in &other.word_frequencies {
+            *self.word_frequencies.entry(word.clone()).or_insert(0) += count;
+        }
+
+        self.line_ending = match (self.line_ending, other.line_ending) {
+            (LineEnding::Unknown, other_ending) => other_ending,
+            (self_ending, LineEnding::Unknown) => self_ending,
+            (a, b) if a == b => a,
+            _ => LineEnding::Mixed,
+        };
+
+        self.indentation = match (self.indentation, other.indentation) {
+            (Indentation::None, other_indent) => other_indent,
+            (self_indent, Indentation::None) => self_indent,
+            (a, b) if a == b => a,
+            _ => Indentation::Mixed,
+        };
+        self.has_mixed_indentation = self.has_mixed_indentation
+            || other.has_mixed_indentation
+            || self.indentation == Indentation::Mixed;
+
+        self.regex_match_count = match (self.regex_match_count, other.regex_match_count) {
+            (Some(a), Some(b)) => Some(a + b),
+            (Some(a), None) => Some(a),
+            (None, Some(b)) => Some(b),
+            (None, None) => None,
+        };
+
+        self.regex_matches = match (self.regex_matches.take(), &other.regex_matches) {
+            (Some(mut a), Some(b)) => {
+                a.extend(b.iter().cloned());
+                Some(a)
+            }
+            (Some(a), None) => Some(a),
+            (None, Some(b)) => Some(b.clone()),
+            (None, None) => None,
+        };
+
+        // Only an approximation across disjoint regions: distinct lines in
+        // each half may overlap, so the true union count can be lower than
+        // this sum.
+        self.unique_line_count = match (self.unique_line_count, other.unique_line_count) {
+            (Some(a), Some(b)) => Some(a + b),
+            (Some(a), None) => Some(a),
+            (None, Some(b)) => Some(b),
+            (None, None) => None,
+        };
+
+        self.fell_back_to_binary = self.fell_back_to_binary || other.fell_back_to_binary;
+        self.invalid_utf8_sequences += other.invalid_utf8_sequences;
+        self.replacement_chars += other.replacement_chars;
+        self.had_bom = self.had_bom || other.had_bom;
+        self.content_hash = self.content_hash.take().or_else(|| other.content_hash.clone());
+        self.language = self.language.take().or_else(|| other.language.clone());
+    }
+
+    /// Renders `char_frequencies` as a `serde_json::Value` object keyed by
+    /// the stringified character (e.g. `"a"`, `" "`, `"\t"`). Control
+    /// and other non-printable characters are not special-cased here: going
+    /// through `serde_json::json!` means every key is written out as a
+    /// proper JSON string, so `serde_json` itself takes care of escaping
+    /// them, the same as `CoreAnalyzer::finish` already relies on.
+    #[allow(dead_code)]
+    pub fn char_freq_json(&self) -> serde_json::Value {
+        let char_frequencies: HashMap<String, usize> = self
+            .char_frequencies
+            .iter()
+            .map(|(ch, count)| (ch.to_string(), *count))
+            .collect();
+
+        serde_json::json!(char_frequencies)
+    }
+}
+
+/// The line-ending convention observed in a file's UTF-8 text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineEnding {
+    /// Every line ended with `\n` only.
+    Lf,
+    /// Every line ended with `\r\n`.
+    CrLf,
+    /// Both `\n` and `\r\n` line endings appeared.
+    Mixed,
+    /// No line endings were observed (empty or single-line file).
+    Unknown,
+}
+
+/// The indentation convention observed in a file's UTF-8 text, based on the
+/// leading whitespace character of each line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Indentation {
+    /// Indented lines consistently start with a space.
+    Spaces,
+    /// Indented lines consistently start with a tab.
+    Tabs,
+    /// Both space- and tab-indented lines appeared.
+    Mixed,
+    /// No indented lines were observed.
+    None,
+}
+
+/// Default `read_buffer_size`, matching the fixed buffer size the binary
+/// fallback path used before it became configurable.
+const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Options controlling which (optionally expensive) statistics
+/// `analyze_file_with_options` collects.
+#[derive(Debug, Clone)]
+pub struct AnalyzeOptions {
+    /// When set, builds a word-frequency map (case-insensitive, punctuation
+    /// trimmed) used to derive `FileStats::most_common_word`. Off by default
+    /// since the map can be large for big vocabularies.
+    pub track_word_frequencies: bool,
+
+    /// When set, analysis stops once this many bytes of (decompressed)
+    /// content have been read, marking `FileStats::truncated` so counts
+    /// are understood to reflect only a sampled prefix rather than the
+    /// whole file. `None` (the default) reads the entire file.
+    pub max_read_bytes: Option<u64>,
+
+    /// When set, measures the analyzing thread's CPU time alongside wall-
+    /// clock time, populating `FileAnalysis::cpu_time`. Off by default
+    /// since per-thread CPU time isn't available on every platform (in
+    /// which case the measurement is silently skipped).
+    pub measure_cpu_time: bool,
+
+    /// When set, buffers distinct non-blank lines in a `HashSet` to
+    /// populate `FileStats::unique_line_count`. Off by default since the
+    /// set can be large for files with many distinct lines.
+    pub track_unique_lines: bool,
+
+    /// Extra characters that split `word_count` tokens, alongside
+    /// whitespace, for formats with their own delimiters (CSV commas,
+    /// pipe-delimited logs, etc). Empty tokens produced by the split are
+    /// dropped. `None` (the default) splits on whitespace only.
+    pub separators: Option<Vec<char>>,
+
+    /// When set, reservoir-samples up to `SampleLinesOptions::k` lines
+    /// uniformly at random during the single read pass, populating
+    /// `FileAnalysis::sample_lines` with a representative preview of a
+    /// giant file without buffering it in full. `None` (the default)
+    /// collects no sample.
+    pub sample_lines: Option<SampleLinesOptions>,
+
+    /// Size in bytes of the read buffer used by the binary fallback path,
+    /// for users on fast storage who want larger reads to cut syscall
+    /// overhead on big binary files. Must be non-zero; a zero value is
+    /// treated as invalid and falls back to `DEFAULT_READ_BUFFER_SIZE`,
+    /// recording a `ProcessingError`. Defaults to 8KiB.
+    pub read_buffer_size: usize,
+
+    /// When set, computes a SHA-256 digest of the file's raw on-disk bytes,
+    /// populating `FileStats::content_hash`, so callers can group files
+    /// with identical content (e.g. a dedup report). Off by default since
+    /// hashing requires a full extra read of the file.
+    pub track_content_hash: bool,
+
+    /// Whether a final line with no trailing `\n` counts towards
+    /// `FileStats::line_count`. A line is defined as content terminated by
+    /// `\n` or EOF; with this on (the default), `line_count` is the number
+    /// of `\n` bytes plus one if the file is non-empty and doesn't end in
+    /// `\n`. This rule is applied identically whether the file is read via
+    /// the UTF-8 or binary fallback path, so the same content reports the
+    /// same count regardless of which path handles it.
+    pub count_trailing_partial_line: bool,
+
+    /// When set, a file that fails UTF-8 decoding is handled by decoding
+    /// it lossily (`String::from_utf8_lossy`, substituting U+FFFD for
+    /// invalid sequences) and running the normal line/word/char analysis
+    /// over the result, instead of dropping to the raw-byte fallback path.
+    /// `FileStats::replacement_chars` records how many substitutions were
+    /// made. Off by default, matching the existing binary-fallback
+    /// behavior.
+    pub lossy_utf8: bool,
+}
+
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        AnalyzeOptions {
+            track_word_frequencies: false,
+            max_read_bytes: None,
+            measure_cpu_time: false,
+            track_unique_lines: false,
+            separators: None,
+            sample_lines: None,
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            track_content_hash: false,
+            count_trailing_partial_line: true,
+            lossy_utf8: false,
         }
     }
 }
 
+/// Configuration for `AnalyzeOptions::sample_lines`.
+#[derive(Debug, Clone, Copy)]
+pub struct SampleLinesOptions {
+    /// Maximum number of lines to keep in the sample.
+    pub k: usize,
+
+    /// Seed for the deterministic RNG used to choose which lines are kept,
+    /// so the same file and seed always produce the same sample (needed
+    /// for reproducible previews and deterministic tests).
+    pub seed: u64,
+
+    /// A file's position in a deterministic traversal order (e.g. its
+    /// index in a sorted file list), mixed into `seed` via
+    /// `seed_for_sequence` so each file's RNG starts from a distinct
+    /// state. Without this, every file sampled in the same run would seed
+    /// its RNG identically and draw the same sequence of "random" reservoir
+    /// replacements, regardless of content. Reusing the same `seed` and
+    /// `sequence_id` for a given file always reproduces the same sample,
+    /// independent of which order threads actually process files in.
+    pub sequence_id: u64,
+}
+
 /// Represents an error that occurred during file processing
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessingError {
     /// Name of the file being processed
+    #[allow(dead_code)]
     pub filename: String,
 
     /// Operation during which the error occurred (e.g., open, read, metadata)
@@ -53,6 +518,7 @@ pub struct ProcessingError {
 
 /// Represents a high-level result of an analysis operation
 /// (not directly used in `analyze_file`, but useful for aggregation)
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct AnalysisResult {
     pub total_files: String,
@@ -61,7 +527,7 @@ pub struct AnalysisResult {
 }
 
 /// Contains the full analysis output for a single file
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileAnalysis {
     /// File path or name
     pub filename: String,
@@ -72,8 +538,209 @@ pub struct FileAnalysis {
     /// List of errors encountered during processing
     pub errors: Vec<ProcessingError>,
 
-    /// Total time taken to analyze the file
+    /// Total wall-clock time taken to analyze the file, serialized as
+    /// milliseconds rather than serde's default `Duration` struct form.
+    #[serde(with = "duration_millis")]
     pub processing_time: Duration,
+
+    /// CPU time consumed by the thread that performed the analysis, as
+    /// opposed to `processing_time`'s wall-clock measurement, so IO-bound
+    /// files (low CPU relative to wall time) can be told apart from
+    /// CPU-bound ones. Only populated when
+    /// `AnalyzeOptions::measure_cpu_time` is set, since the underlying
+    /// `getrusage`-style call isn't available on every platform.
+    #[serde(default, with = "duration_millis_opt")]
+    pub cpu_time: Option<Duration>,
+
+    /// Lines chosen by reservoir sampling when `AnalyzeOptions::sample_lines`
+    /// is set, for a representative preview of a giant file without
+    /// reading it in full. Empty unless sampling was requested.
+    #[serde(default)]
+    pub sample_lines: Vec<String>,
+}
+
+/// Serializes a `Duration` as an integer number of milliseconds.
+mod duration_millis {
+    use super::Duration;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        (duration.as_millis() as u64).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let millis = u64::deserialize(deserializer)?;
+        Ok(Duration::from_millis(millis))
+    }
+}
+
+/// Like `duration_millis`, but for an optional duration (`FileAnalysis::cpu_time`).
+mod duration_millis_opt {
+    use super::Duration;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        duration.map(|d| d.as_millis() as u64).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        let millis = Option::<u64>::deserialize(deserializer)?;
+        Ok(millis.map(Duration::from_millis))
+    }
+}
+
+/// Analyzes a file at the given path using the default options.
+/// See `analyze_file_with_options` for details.
+pub fn analyze_file(path: &str) -> FileAnalysis {
+    analyze_file_with_options(path, &AnalyzeOptions::default())
+}
+
+/// Analyzes data from an arbitrary reader rather than a file on disk, for
+/// sources like tar archive entries that have no path to re-open for a
+/// second pass. `reader` is fully buffered in memory, then counted the
+/// same way as the plain-file path: UTF-8 text if the whole buffer
+/// decodes cleanly, otherwise a raw-byte fallback. Per-line classification
+/// (line endings, indentation, word/char counts) shares its rules with
+/// `analyze_file_with_options` via the `tally_*`/`finalize_*` helpers
+/// below, so the two paths can't quietly drift apart.
+///
+/// This function takes no `AnalyzeOptions`, so it always behaves as if
+/// `count_trailing_partial_line` were at its default of `true`: a
+/// trailing line with no `\n` terminator is still counted, since
+/// `split_inclusive` yields it the same as any other line (and the
+/// raw-byte fallback adds one to its `\n` count when the buffer doesn't
+/// end in `\n`).
+pub fn analyze_reader(filename: &str, mut reader: impl Read, size_bytes: u64) -> FileAnalysis {
+    let start = Instant::now();
+
+    let mut stats = FileStats::new();
+    let mut errors = Vec::new();
+    stats.size_bytes = size_bytes;
+    stats.is_empty = size_bytes == 0;
+
+    let mut buf = Vec::new();
+    if let Err(e) = reader.read_to_end(&mut buf) {
+        stats.bytes_read = buf.len() as u64;
+        errors.push(ProcessingError {
+            filename: filename.to_string(),
+            operation: "read".to_string(),
+            message: format!("{:?}", e),
+        });
+        return FileAnalysis {
+            filename: filename.to_string(),
+            stats,
+            errors,
+            processing_time: start.elapsed(),
+            cpu_time: None,
+            sample_lines: Vec::new(),
+        };
+    }
+    stats.bytes_read = buf.len() as u64;
+
+    let mut total_words = 0usize;
+    let mut total_lines = 0usize;
+    let mut freq: HashMap<char, usize> = HashMap::new();
+    let mut char_classes = CharClassCounts::default();
+    let mut lf_count = 0usize;
+    let mut crlf_count = 0usize;
+    let mut space_indent_lines = 0usize;
+    let mut tab_indent_lines = 0usize;
+    let mut longest_line_len = 0usize;
+    let mut longest_line_no = 0usize;
+    let utf8_ok = std::str::from_utf8(&buf).is_ok();
+    let mut had_bom = false;
+    let mut first_line = String::new();
+    let mut single_line_mixed_indent = false;
+
+    match std::str::from_utf8(&buf) {
+        Ok(text) => {
+            // Strip a leading UTF-8 byte-order mark before counting, so it
+            // isn't folded into `char_frequencies` or `longest_line_len`.
+            let text = match text.strip_prefix('\u{feff}') {
+                Some(rest) => {
+                    had_bom = true;
+                    rest
+                }
+                None => text,
+            };
+            // `analyze_reader` has no `AnalyzeOptions`, so unlike
+            // `analyze_file_with_options` it always credits a trailing
+            // line with no terminator, matching that function's default
+            // `count_trailing_partial_line: true` (`split_inclusive`
+            // yields that final line the same as every other one).
+            for line in text.split_inclusive('\n') {
+                total_lines += 1;
+                if total_lines == 1 {
+                    first_line = line.to_string();
+                }
+                tally_line_ending(line, &mut lf_count, &mut crlf_count);
+                tally_indentation(
+                    line,
+                    &mut space_indent_lines,
+                    &mut tab_indent_lines,
+                    &mut single_line_mixed_indent,
+                );
+                total_words += count_words(line, None);
+                tally_longest_line(line, total_lines, &mut longest_line_len, &mut longest_line_no);
+                tally_chars(line, &mut freq, &mut char_classes);
+            }
+        }
+        Err(_) => {
+            for &b in &buf {
+                let ch = b as char;
+                *freq.entry(ch).or_insert(0) += 1;
+                char_classes.record(ch);
+            }
+            total_lines = buf.iter().filter(|&&c| c == b'\n').count();
+            if buf.last().is_some_and(|&b| b != b'\n') {
+                total_lines += 1;
+            }
+            total_words = buf
+                .split(|&c| c == b' ' || c == b'\n' || c == b'\t' || c == b'\r')
+                .filter(|slice| !slice.is_empty())
+                .count();
+
+            let mut line_no = 0usize;
+            for chunk in buf.split(|&b| b == b'\n') {
+                line_no += 1;
+                let line_len = chunk
+                    .strip_suffix(b"\r")
+                    .unwrap_or(chunk)
+                    .len();
+                if line_len > longest_line_len {
+                    longest_line_len = line_len;
+                    longest_line_no = line_no;
+                }
+            }
+        }
+    }
+
+    stats.word_count = total_words;
+    stats.line_count = total_lines;
+    stats.char_frequencies = freq;
+    stats.char_classes = char_classes;
+    stats.longest_line_len = longest_line_len;
+    stats.longest_line_no = longest_line_no;
+    stats.fell_back_to_binary = !utf8_ok;
+    if !utf8_ok {
+        stats.invalid_utf8_sequences = count_invalid_utf8_sequences(&buf);
+    }
+    stats.had_bom = had_bom;
+    stats.detected_type = detect_type_from_bytes(&buf);
+    stats.language = guess_language(filename, &first_line);
+    stats.line_ending = finalize_line_ending(lf_count, crlf_count);
+    stats.indentation = finalize_indentation(tab_indent_lines, space_indent_lines);
+    stats.has_mixed_indentation =
+        stats.indentation == Indentation::Mixed || single_line_mixed_indent;
+
+    FileAnalysis {
+        filename: filename.to_string(),
+        stats,
+        errors,
+        processing_time: start.elapsed(),
+        cpu_time: None,
+        sample_lines: Vec::new(),
+    }
 }
 
 /// Analyzes a file at the given path and returns a `FileAnalysis`
@@ -82,9 +749,14 @@ pub struct FileAnalysis {
 /// 1. Read file metadata (size)
 /// 2. Read the file as UTF-8 text line-by-line
 /// 3. Fall back to raw binary processing if UTF-8 decoding fails
-pub fn analyze_file(path: &str) -> FileAnalysis {
+pub fn analyze_file_with_options(path: &str, options: &AnalyzeOptions) -> FileAnalysis {
     // Start timing the analysis
     let start = Instant::now();
+    let cpu_start = if options.measure_cpu_time {
+        ThreadTime::try_now().ok()
+    } else {
+        None
+    };
 
     // Initialize statistics and error tracking
     let mut stats = FileStats::new();
@@ -94,6 +766,7 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
     match std::fs::metadata(path) {
         Ok(metadata) => {
             stats.size_bytes = metadata.len();
+            stats.is_empty = metadata.len() == 0;
         }
         Err(e) => {
             errors.push(ProcessingError {
@@ -105,7 +778,7 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
     }
 
     // Attempt to open the file
-    let file = match File::open(path) {
+    let mut file = match open_for_content(path) {
         Ok(f) => f,
         Err(e) => {
             // If the file cannot be opened, return early
@@ -119,17 +792,84 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
                 stats,
                 errors,
                 processing_time: start.elapsed(),
+                cpu_time: cpu_start.and_then(|t| t.try_elapsed().ok()),
+                sample_lines: Vec::new(),
             };
         }
     };
 
-    // Wrap the file in a buffered reader for efficient line-by-line reading
-    let mut reader = BufReader::new(file);
+    // Determine whether this file is gzip-compressed, either by extension
+    // or by sniffing the two-byte gzip magic number (0x1f 0x8b).
+    let is_gzip = path.ends_with(".gz") || sniff_gzip_magic(path);
+
+    // Gzip takes priority: a compressed UTF-16 file is vanishingly rare,
+    // and sniffing the BOM would require decompressing first anyway.
+    let utf16_encoding = if is_gzip { None } else { sniff_utf16_bom(path) };
+
+    // For plain (non-gzip, non-UTF-16) files under `BUFFERED_READ_CAP_BYTES`,
+    // the whole content is read into memory once up front and kept around
+    // in `buffered`, so that if the UTF-8 attempt below fails, the binary
+    // fallback can re-scan the same bytes instead of reopening and
+    // re-reading the file from disk a second time. Larger files keep
+    // streaming, since buffering them fully would cost more memory than
+    // the fallback's extra read would cost IO.
+    let mut buffered: Option<Vec<u8>> = None;
+    if !is_gzip && utf16_encoding.is_none() && stats.size_bytes <= BUFFERED_READ_CAP_BYTES {
+        let mut raw = Vec::new();
+        if file.read_to_end(&mut raw).is_ok() {
+            buffered = Some(raw);
+        }
+    }
+
+    // Wrap the file (or the in-memory buffer, for the buffered case above)
+    // in a buffered reader for efficient line-by-line reading. Gzip input
+    // is transparently decompressed first; a UTF-16 BOM is transcoded to
+    // UTF-8 up front so the line loop below sees ordinary UTF-8 text
+    // instead of falling back to the byte-oriented binary path.
+    let mut reader: Box<dyn BufRead> = if let Some(buf) = &buffered {
+        Box::new(BufReader::new(Cursor::new(buf.clone())))
+    } else if is_gzip {
+        Box::new(BufReader::new(GzDecoder::new(file)))
+    } else if let Some(encoding) = utf16_encoding {
+        let mut raw = Vec::new();
+        match file.read_to_end(&mut raw) {
+            Ok(_) => {
+                let (text, _, _had_errors) = encoding.decode(&raw);
+                Box::new(BufReader::new(Cursor::new(text.into_owned().into_bytes())))
+            }
+            Err(e) => {
+                errors.push(ProcessingError {
+                    filename: path.to_string(),
+                    operation: "read".to_string(),
+                    message: format!("{:?}", e),
+                });
+                Box::new(BufReader::new(Cursor::new(Vec::new())))
+            }
+        }
+    } else {
+        Box::new(BufReader::new(file))
+    };
 
     // Accumulators for analysis results
     let mut total_words = 0usize;
     let mut total_lines = 0usize;
     let mut freq: HashMap<char, usize> = HashMap::new();
+    let mut char_classes = CharClassCounts::default();
+    let mut word_freq: HashMap<String, usize> = HashMap::new();
+    let mut unique_lines: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut sample_lines: Vec<String> = Vec::new();
+    let mut sample_rng = options
+        .sample_lines
+        .map(|cfg| Xorshift64::new(seed_for_sequence(cfg.seed, cfg.sequence_id)));
+    let mut decompressed_bytes = 0u64;
+    let mut lf_count = 0usize;
+    let mut crlf_count = 0usize;
+    let mut space_indent_lines = 0usize;
+    let mut tab_indent_lines = 0usize;
+    let mut lines_with_trailing_ws = 0usize;
+    let mut longest_line_len = 0usize;
+    let mut longest_line_no = 0usize;
+    let mut first_line = String::new();
 
     // Buffer for reading lines
     let mut line_buf = String::new();
@@ -137,23 +877,188 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
     // Flag indicating whether UTF-8 reading was successful
     let mut utf8_ok = true;
 
+    // True once a leading UTF-8 byte-order mark has been stripped from the
+    // first line, so it isn't counted as a character or mistaken for
+    // indentation further down.
+    let mut had_bom = false;
+
+    // True once `options.max_read_bytes` has been reached, meaning the
+    // counts reflect only a sampled prefix of the file.
+    let mut truncated = false;
+
+    // Whether the most recently read line ended in `\n`, so a trailing
+    // line with no terminator can be credited (or not) per
+    // `options.count_trailing_partial_line` once reading stops.
+    let mut last_line_had_newline = true;
+
+    // Number of U+FFFD substitutions made while lossily re-decoding the
+    // file under `options.lossy_utf8`; stays 0 unless that retry happens.
+    let mut replacement_chars = 0usize;
+
+    // Set once the lossy re-decode below has already been attempted, so a
+    // second invalid sequence in the (now always-valid) decoded text can't
+    // trigger it again.
+    let mut lossy_retried = false;
+
+    // Number of distinct invalid UTF-8 byte spans found so far; populated
+    // either by the lossy re-decode below or the binary fallback path
+    // further down, whichever one ends up handling the invalid input.
+    let mut invalid_utf8_sequences = 0usize;
+
+    // Set once any single line's leading whitespace mixes tabs and spaces,
+    // regardless of whether the file's dominant indentation is otherwise
+    // consistent.
+    let mut single_line_mixed_indent = false;
+
     // Read the file line by line (UTF-8 path)
     loop {
+        if let Some(budget) = options.max_read_bytes
+            && decompressed_bytes >= budget
+        {
+            truncated = true;
+            break;
+        }
+
         line_buf.clear();
         match reader.read_line(&mut line_buf) {
             Ok(0) => break, // End of file
-            Ok(_) => {
+            Ok(n) => {
                 total_lines += 1;
+                decompressed_bytes += n as u64;
+
+                // Strip a leading UTF-8 byte-order mark from the very first
+                // line before any further processing, so it isn't counted
+                // into `char_frequencies` or mistaken for indentation.
+                if total_lines == 1 && line_buf.starts_with('\u{feff}') {
+                    had_bom = true;
+                    line_buf.remove(0);
+                }
+                if total_lines == 1 {
+                    first_line = line_buf.clone();
+                }
+
+                // Track line-ending and indentation style
+                last_line_had_newline = line_buf.ends_with('\n');
+                tally_line_ending(&line_buf, &mut lf_count, &mut crlf_count);
+                tally_indentation(
+                    &line_buf,
+                    &mut space_indent_lines,
+                    &mut tab_indent_lines,
+                    &mut single_line_mixed_indent,
+                );
 
-                // Count words using whitespace separation
-                total_words += line_buf.split_whitespace().count();
+                // Track trailing whitespace for the code-quality feature,
+                // ignoring the line terminator itself.
+                let trimmed_line = line_buf.trim_end_matches(['\n', '\r']);
+                if trimmed_line.ends_with([' ', '\t']) {
+                    lines_with_trailing_ws += 1;
+                }
+
+                // Track the longest line seen so far, keeping the first
+                // occurrence on ties.
+                tally_longest_line(&line_buf, total_lines, &mut longest_line_len, &mut longest_line_no);
+
+                // Count words, splitting on whitespace plus any configured
+                // `separators` (e.g. commas, pipes), dropping empty tokens.
+                total_words += count_words(&line_buf, options.separators.as_deref());
 
                 // Count character frequencies
-                for ch in line_buf.chars() {
-                    *freq.entry(ch).or_insert(0) += 1;
+                tally_chars(&line_buf, &mut freq, &mut char_classes);
+
+                // Track per-word frequency for the word-mode feature,
+                // normalized to lowercase with surrounding punctuation trimmed.
+                if options.track_word_frequencies {
+                    for word in line_buf.split_whitespace() {
+                        let normalized = word
+                            .trim_matches(|c: char| !c.is_alphanumeric())
+                            .to_lowercase();
+                        if !normalized.is_empty() {
+                            *word_freq.entry(normalized).or_insert(0) += 1;
+                        }
+                    }
+                }
+
+                // Track distinct non-blank lines for the log-deduplication
+                // feature, trimming the trailing newline before hashing.
+                if options.track_unique_lines {
+                    let trimmed = line_buf.trim_end_matches(['\n', '\r']);
+                    if !trimmed.is_empty() {
+                        unique_lines.insert(trimmed.to_string());
+                    }
+                }
+
+                // Reservoir-sample lines for a representative preview,
+                // via Algorithm R: the first `k` lines always fill the
+                // reservoir; after that, line `i` replaces a uniformly
+                // random existing slot with probability `k / i`.
+                if let (Some(cfg), Some(rng)) = (options.sample_lines, sample_rng.as_mut()) {
+                    let trimmed = trimmed_line.to_string();
+                    if sample_lines.len() < cfg.k {
+                        sample_lines.push(trimmed);
+                    } else if cfg.k > 0 {
+                        let j = rng.below(total_lines as u64) as usize;
+                        if j < cfg.k {
+                            sample_lines[j] = trimmed;
+                        }
+                    }
                 }
             }
             Err(e) => {
+                if options.lossy_utf8 && !lossy_retried {
+                    // Re-decode the whole file lossily and restart this same
+                    // loop over the result, which is now guaranteed valid
+                    // UTF-8, so the rest of this function needs no separate
+                    // byte-oriented code path for `--lossy`.
+                    lossy_retried = true;
+
+                    let raw_bytes: Vec<u8> = if let Some(buf) = &buffered {
+                        buf.clone()
+                    } else {
+                        let mut raw = Vec::new();
+                        let read_result = match open_for_content(path) {
+                            Ok(f) if is_gzip => GzDecoder::new(f).read_to_end(&mut raw),
+                            Ok(mut f) => f.read_to_end(&mut raw),
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = read_result {
+                            errors.push(ProcessingError {
+                                filename: path.to_string(),
+                                operation: "read".to_string(),
+                                message: format!("{:?}", e),
+                            });
+                        }
+                        raw
+                    };
+
+                    let decoded = String::from_utf8_lossy(&raw_bytes).into_owned();
+                    replacement_chars = decoded.matches('\u{fffd}').count();
+                    invalid_utf8_sequences = count_invalid_utf8_sequences(&raw_bytes);
+
+                    reader = Box::new(BufReader::new(Cursor::new(decoded.into_bytes())));
+                    total_lines = 0;
+                    total_words = 0;
+                    freq.clear();
+                    char_classes = CharClassCounts::default();
+                    lines_with_trailing_ws = 0;
+                    longest_line_len = 0;
+                    longest_line_no = 0;
+                    decompressed_bytes = 0;
+                    lf_count = 0;
+                    crlf_count = 0;
+                    space_indent_lines = 0;
+                    tab_indent_lines = 0;
+                    single_line_mixed_indent = false;
+                    word_freq.clear();
+                    unique_lines.clear();
+                    sample_lines.clear();
+                    sample_rng = options
+                        .sample_lines
+                        .map(|cfg| Xorshift64::new(seed_for_sequence(cfg.seed, cfg.sequence_id)));
+                    first_line.clear();
+                    had_bom = false;
+                    continue;
+                }
+
                 // UTF-8 decoding failed; switch to binary fallback
                 utf8_ok = false;
                 errors.push(ProcessingError {
@@ -166,23 +1071,103 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
         }
     }
 
+    // A trailing line with no `\n` terminator was counted as a line above
+    // by virtue of its own `read_line` call; back it out when the canonical
+    // rule says not to credit it, matching the binary path below.
+    if utf8_ok && !options.count_trailing_partial_line && total_lines > 0 && !last_line_had_newline
+    {
+        total_lines -= 1;
+    }
+
     // ----------------------------
     // Binary fallback path
     // ----------------------------
-    // If UTF-8 reading failed, re-read the file as raw bytes
+    // If UTF-8 reading failed, re-scan the file as raw bytes. When the
+    // whole file was already buffered in memory above, this reuses that
+    // same buffer instead of reopening and re-reading it from disk.
     if !utf8_ok {
-        if let Ok(mut f) = std::fs::File::open(path) {
-            let mut buf = [0u8; 8 * 1024];
+        // The UTF-8 attempt above may have already counted some lines,
+        // words, and bytes before it hit invalid input partway through the
+        // file; since the raw re-scan below covers the whole file from the
+        // start, reset those counters so the binary path's results replace
+        // rather than double-count on top of the aborted attempt. This must
+        // mirror the reset the `--lossy` retry above performs, since both
+        // are discarding the same partial UTF-8 pass.
+        total_lines = 0;
+        total_words = 0;
+        freq.clear();
+        char_classes = CharClassCounts::default();
+        lines_with_trailing_ws = 0;
+        longest_line_len = 0;
+        longest_line_no = 0;
+        decompressed_bytes = 0;
+        lf_count = 0;
+        crlf_count = 0;
+        space_indent_lines = 0;
+        tab_indent_lines = 0;
+        single_line_mixed_indent = false;
+        word_freq.clear();
+        unique_lines.clear();
+        // `sample_rng` is left alone: the binary rescan below never takes
+        // line samples, so there's nothing downstream that would read a
+        // reset value.
+        sample_lines.clear();
+        first_line.clear();
+        had_bom = false;
+
+        let raw: Option<Box<dyn Read>> = if let Some(buf) = &buffered {
+            Some(Box::new(Cursor::new(buf.clone())))
+        } else {
+            match open_for_content(path) {
+                Ok(f) if is_gzip => Some(Box::new(GzDecoder::new(f))),
+                Ok(f) => Some(Box::new(f)),
+                Err(_) => None,
+            }
+        };
+
+        if let Some(mut f) = raw {
+            let read_buffer_size = if options.read_buffer_size == 0 {
+                errors.push(ProcessingError {
+                    filename: path.to_string(),
+                    operation: "options".to_string(),
+                    message: format!(
+                        "read_buffer_size must be non-zero; using default {}",
+                        DEFAULT_READ_BUFFER_SIZE
+                    ),
+                });
+                DEFAULT_READ_BUFFER_SIZE
+            } else {
+                options.read_buffer_size
+            };
+            let mut buf = vec![0u8; read_buffer_size];
+            let mut raw_line_no = 0usize;
+            let mut raw_line_len = 0usize;
+            let mut raw_in_word = false;
+            let mut raw_bom_prefix: Vec<u8> = Vec::with_capacity(3);
+            let mut raw_bom_checked = false;
+            let mut raw_bytes_seen = 0u64;
+            let mut raw_at_line_start = true;
+            let mut raw_line_first_byte: Option<u8> = None;
+            let mut raw_leading_ws_done = false;
+            let mut raw_line_has_tab = false;
+            let mut raw_line_has_space = false;
+            let mut raw_prev_byte: Option<u8> = None;
+            let mut raw_first_line_bytes: Vec<u8> = Vec::new();
+            let mut raw_capturing_first_line = true;
 
             loop {
+                if let Some(budget) = options.max_read_bytes
+                    && decompressed_bytes >= budget
+                {
+                    truncated = true;
+                    break;
+                }
+
                 match f.read(&mut buf) {
                     Ok(0) => break, // End of file
                     Ok(n) => {
-                        // Count byte-level character frequencies
-                        for &b in &buf[..n] {
-                            let ch = b as char;
-                            *freq.entry(ch).or_insert(0) += 1;
-                        }
+                        decompressed_bytes += n as u64;
+                        invalid_utf8_sequences += count_invalid_utf8_sequences(&buf[..n]);
 
                         // Count newline characters as lines
                         total_lines += buf[..n]
@@ -190,11 +1175,109 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
                             .filter(|&&c| c == b'\n')
                             .count();
 
-                        // Count words by splitting on common whitespace bytes
-                        total_words += buf[..n]
-                            .split(|&c| c == b' ' || c == b'\n' || c == b'\t' || c == b'\r')
-                            .filter(|slice| !slice.is_empty())
-                            .count();
+                        // Peek the first three bytes of the file for a UTF-8
+                        // BOM, the same marker the text path strips before
+                        // counting, so a binary-fallback file that happens
+                        // to start with one still reports `had_bom` and
+                        // excludes those bytes below, instead of folding
+                        // them into `char_frequencies` or the first line's
+                        // indentation.
+                        if !raw_bom_checked {
+                            for &b in &buf[..n] {
+                                if raw_bom_prefix.len() < 3 {
+                                    raw_bom_prefix.push(b);
+                                }
+                                if raw_bom_prefix.len() == 3 {
+                                    had_bom = raw_bom_prefix == [0xEF, 0xBB, 0xBF];
+                                    raw_bom_checked = true;
+                                    break;
+                                }
+                            }
+                        }
+
+                        // Count byte-level character frequencies, track the
+                        // longest completed line, each line's
+                        // leading-whitespace style and terminator, and the
+                        // file's first line (for the shebang-based language
+                        // guess), all carrying state across chunk
+                        // boundaries the same way `raw_line_len` does.
+                        for &b in &buf[..n] {
+                            raw_bytes_seen += 1;
+                            let in_bom = had_bom && raw_bytes_seen <= 3;
+                            if in_bom {
+                                raw_prev_byte = Some(b);
+                                continue;
+                            }
+
+                            let ch = b as char;
+                            *freq.entry(ch).or_insert(0) += 1;
+                            char_classes.record(ch);
+
+                            if raw_capturing_first_line {
+                                if b == b'\n' {
+                                    raw_capturing_first_line = false;
+                                } else {
+                                    raw_first_line_bytes.push(b);
+                                }
+                            }
+
+                            if raw_at_line_start {
+                                raw_line_first_byte = Some(b);
+                                raw_at_line_start = false;
+                            }
+                            if !raw_leading_ws_done {
+                                match b {
+                                    b'\t' => raw_line_has_tab = true,
+                                    b' ' => raw_line_has_space = true,
+                                    _ => raw_leading_ws_done = true,
+                                }
+                            }
+
+                            if b == b'\n' {
+                                raw_line_no += 1;
+                                if raw_line_len > longest_line_len {
+                                    longest_line_len = raw_line_len;
+                                    longest_line_no = raw_line_no;
+                                }
+                                if raw_prev_byte == Some(b'\r') {
+                                    crlf_count += 1;
+                                } else {
+                                    lf_count += 1;
+                                }
+                                match raw_line_first_byte {
+                                    Some(b'\t') => tab_indent_lines += 1,
+                                    Some(b' ') => space_indent_lines += 1,
+                                    _ => {}
+                                }
+                                if raw_line_has_tab && raw_line_has_space {
+                                    single_line_mixed_indent = true;
+                                }
+                                raw_line_len = 0;
+                                raw_at_line_start = true;
+                                raw_line_first_byte = None;
+                                raw_leading_ws_done = false;
+                                raw_line_has_tab = false;
+                                raw_line_has_space = false;
+                            } else {
+                                raw_line_len += 1;
+                            }
+                            raw_prev_byte = Some(b);
+                        }
+
+                        // Count words by tracking whether we're inside a word
+                        // across the whole stream, rather than splitting each
+                        // chunk independently, so a word split across a read
+                        // boundary isn't double-counted when the buffer is
+                        // smaller than the file.
+                        for &b in &buf[..n] {
+                            let is_ws = b == b' ' || b == b'\n' || b == b'\t' || b == b'\r';
+                            if is_ws {
+                                raw_in_word = false;
+                            } else if !raw_in_word {
+                                raw_in_word = true;
+                                total_words += 1;
+                            }
+                        }
                     }
                     Err(e2) => {
                         errors.push(ProcessingError {
@@ -206,6 +1289,33 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
                     }
                 }
             }
+
+            // A trailing run of bytes with no closing `\n` is a partial
+            // line per the canonical rule (same as the UTF-8 path above);
+            // credit it when `options.count_trailing_partial_line` says to.
+            if options.count_trailing_partial_line && raw_line_len > 0 {
+                total_lines += 1;
+            }
+            // Unlike the line count above, a dangling partial line's own
+            // indentation always counts, matching `split_inclusive('\n')`
+            // on the text path, which yields that line regardless of
+            // whether it ends in a newline.
+            if raw_line_len > 0 {
+                match raw_line_first_byte {
+                    Some(b'\t') => tab_indent_lines += 1,
+                    Some(b' ') => space_indent_lines += 1,
+                    _ => {}
+                }
+                if raw_line_has_tab && raw_line_has_space {
+                    single_line_mixed_indent = true;
+                }
+            }
+            if !raw_capturing_first_line || !raw_first_line_bytes.is_empty() {
+                first_line = String::from_utf8_lossy(&raw_first_line_bytes).into_owned();
+                if !raw_capturing_first_line {
+                    first_line.push('\n');
+                }
+            }
         }
     }
 
@@ -213,6 +1323,47 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
     stats.word_count = total_words;
     stats.line_count = total_lines;
     stats.char_frequencies = freq;
+    stats.char_classes = char_classes;
+    stats.truncated = truncated;
+    stats.lines_with_trailing_ws = lines_with_trailing_ws;
+    stats.longest_line_len = longest_line_len;
+    stats.longest_line_no = longest_line_no;
+    stats.fell_back_to_binary = !utf8_ok;
+    stats.invalid_utf8_sequences = invalid_utf8_sequences;
+    stats.replacement_chars = replacement_chars;
+    stats.had_bom = had_bom;
+    if options.track_content_hash {
+        stats.content_hash = hash_file(path);
+    }
+    stats.detected_type = detect_file_type(path);
+    stats.bytes_read = decompressed_bytes;
+    stats.language = guess_language(path, &first_line);
+    if is_gzip {
+        stats.decompressed_size_bytes = Some(decompressed_bytes);
+    }
+    stats.line_ending = finalize_line_ending(lf_count, crlf_count);
+    stats.indentation = finalize_indentation(tab_indent_lines, space_indent_lines);
+    stats.has_mixed_indentation =
+        stats.indentation == Indentation::Mixed || single_line_mixed_indent;
+    if options.track_word_frequencies {
+        let mut best: Option<(String, usize)> = None;
+        for (word, &count) in &word_freq {
+            let is_better = match &best {
+                None => true,
+                Some((best_word, best_count)) => {
+                    count > *best_count || (count == *best_count && word < best_word)
+                }
+            };
+            if is_better {
+                best = Some((word.clone(), count));
+            }
+        }
+        stats.most_common_word = best;
+        stats.word_frequencies = word_freq;
+    }
+    if options.track_unique_lines {
+        stats.unique_line_count = Some(unique_lines.len());
+    }
 
     // Return the completed analysis
     FileAnalysis {
@@ -220,5 +1371,902 @@ pub fn analyze_file(path: &str) -> FileAnalysis {
         stats,
         errors,
         processing_time: start.elapsed(),
+        cpu_time: cpu_start.and_then(|t| t.try_elapsed().ok()),
+        sample_lines,
+    }
+}
+
+/// Files at least this large are analyzed in parallel across several
+/// threads instead of with a single linear pass; see `analyze_file_chunked`.
+pub const CHUNKED_ANALYSIS_THRESHOLD_BYTES: u64 = 1024 * 1024;
+
+/// Analyzes a file the same way as `analyze_file`, but for files at least
+/// `CHUNKED_ANALYSIS_THRESHOLD_BYTES` splits the work across several
+/// threads so one huge file doesn't leave other workers idle.
+///
+/// The file is split into byte ranges aligned to line boundaries (each
+/// range boundary is pushed forward to the next `\n`), so no word is
+/// double-counted or missed at a chunk seam. Only `word_count`,
+/// `line_count`, `char_frequencies`, `size_bytes`, `bytes_read`, and
+/// `is_empty` are computed this way, plus `char_classes`, `detected_type`
+/// (which only reads a small fixed prefix regardless of file size, so it's
+/// cheap to compute outside the chunked pass), and `language` (guessed
+/// from the extension alone; no shebang line is read). The remaining
+/// stats (line ending, indentation, longest line, and so on) keep their
+/// `FileStats::new` defaults on this path; gzip input and files under the
+/// threshold fall back to `analyze_file`, which fills those in as well.
+pub fn analyze_file_chunked(path: &str) -> FileAnalysis {
+    let metadata = match std::fs::metadata(path) {
+        Ok(m) => m,
+        Err(_) => return analyze_file(path),
+    };
+
+    let is_gzip = path.ends_with(".gz") || sniff_gzip_magic(path);
+    if is_gzip || metadata.len() < CHUNKED_ANALYSIS_THRESHOLD_BYTES {
+        return analyze_file(path);
     }
+
+    let start = Instant::now();
+    let size = metadata.len();
+    let num_chunks = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .clamp(2, 8);
+
+    let boundaries = chunk_boundaries(path, size, num_chunks);
+
+    let chunk_results: Vec<(usize, usize, HashMap<char, usize>, CharClassCounts)> =
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = boundaries
+                .windows(2)
+                .map(|w| {
+                    let (chunk_start, chunk_end) = (w[0], w[1]);
+                    scope.spawn(move || analyze_chunk(path, chunk_start, chunk_end))
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+    let mut word_count = 0usize;
+    let mut line_count = 0usize;
+    let mut char_frequencies: HashMap<char, usize> = HashMap::new();
+    let mut char_classes = CharClassCounts::default();
+    for (words, lines, freq, classes) in chunk_results {
+        word_count += words;
+        line_count += lines;
+        for (ch, count) in freq {
+            *char_frequencies.entry(ch).or_insert(0) += count;
+        }
+        char_classes.merge(&classes);
+    }
+
+    let mut stats = FileStats::new();
+    stats.size_bytes = size;
+    stats.bytes_read = size;
+    stats.is_empty = size == 0;
+    stats.word_count = word_count;
+    stats.line_count = line_count;
+    stats.char_frequencies = char_frequencies;
+    stats.char_classes = char_classes;
+    stats.detected_type = detect_file_type(path);
+    stats.language = guess_language(path, "");
+
+    FileAnalysis {
+        filename: path.to_string(),
+        stats,
+        errors: Vec::new(),
+        processing_time: start.elapsed(),
+        cpu_time: None,
+        sample_lines: Vec::new(),
+    }
+}
+
+/// Analyzes only the byte range `[start, end)` of the file at `path`,
+/// for spot-checking or sampling a slice of a large file (e.g. the tail of
+/// a log) without reading it in full. `end` is clamped to the file's size.
+/// Word, line, and character-frequency counts reflect only the sampled
+/// range, the same way `analyze_file_chunked`'s per-chunk counts do;
+/// `truncated` is always set, since the result never covers the whole file.
+pub fn analyze_file_range(path: &str, start: u64, end: u64) -> FileAnalysis {
+    let timer = Instant::now();
+    let mut stats = FileStats::new();
+    let mut errors = Vec::new();
+
+    let size = match std::fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+            errors.push(ProcessingError {
+                filename: path.to_string(),
+                operation: "metadata".to_string(),
+                message: format!("{:?}", e),
+            });
+            return FileAnalysis {
+                filename: path.to_string(),
+                stats,
+                errors,
+                processing_time: timer.elapsed(),
+                cpu_time: None,
+                sample_lines: Vec::new(),
+            };
+        }
+    };
+
+    let end = end.min(size);
+    let start = start.min(end);
+    let (words, lines, freq, classes) = analyze_chunk(path, start, end);
+
+    stats.size_bytes = size;
+    stats.bytes_read = end - start;
+    stats.word_count = words;
+    stats.line_count = lines;
+    stats.char_frequencies = freq;
+    stats.char_classes = classes;
+    stats.truncated = true;
+    stats.detected_type = detect_file_type(path);
+    stats.language = guess_language(path, "");
+
+    FileAnalysis {
+        filename: path.to_string(),
+        stats,
+        errors,
+        processing_time: timer.elapsed(),
+        cpu_time: None,
+        sample_lines: Vec::new(),
+    }
+}
+
+/// Computes `num_chunks + 1` byte offsets splitting `[0, size)` into
+/// `num_chunks` line-aligned ranges: offset `0`, offset `size`, and
+/// `num_chunks - 1` intermediate offsets each pushed forward from an
+/// even split point to the start of the next line, so no chunk begins or
+/// ends mid-line (and therefore mid-word).
+fn chunk_boundaries(path: &str, size: u64, num_chunks: usize) -> Vec<u64> {
+    let mut boundaries = vec![0u64];
+
+    if let Ok(mut file) = File::open(path) {
+        for i in 1..num_chunks {
+            let naive = size * i as u64 / num_chunks as u64;
+            if file.seek(SeekFrom::Start(naive)).is_err() {
+                continue;
+            }
+            let mut reader = BufReader::new(&mut file);
+            let mut discard = String::new();
+            if let Ok(n) = reader.read_line(&mut discard)
+                && n > 0
+            {
+                boundaries.push((naive + n as u64).min(size));
+            }
+        }
+    }
+
+    boundaries.push(size);
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    boundaries
+}
+
+/// Analyzes the line-aligned byte range `[start, end)` of the file at
+/// `path`, returning its word count, line count, character frequencies,
+/// and character-class counts for merging into the overall `FileStats`.
+fn analyze_chunk(
+    path: &str,
+    start: u64,
+    end: u64,
+) -> (usize, usize, HashMap<char, usize>, CharClassCounts) {
+    let mut words = 0usize;
+    let mut lines = 0usize;
+    let mut freq: HashMap<char, usize> = HashMap::new();
+    let mut classes = CharClassCounts::default();
+
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return (words, lines, freq, classes),
+    };
+    let mut reader = BufReader::new(file);
+    if reader.seek(SeekFrom::Start(start)).is_err() {
+        return (words, lines, freq, classes);
+    }
+
+    let mut remaining = end - start;
+    let mut line_buf = String::new();
+    while remaining > 0 {
+        line_buf.clear();
+        match reader.read_line(&mut line_buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                let n = n as u64;
+                lines += 1;
+                words += line_buf.split_whitespace().count();
+                for ch in line_buf.chars() {
+                    *freq.entry(ch).or_insert(0) += 1;
+                    classes.record(ch);
+                }
+                remaining = remaining.saturating_sub(n);
+            }
+            Err(_) => break,
+        }
+    }
+
+    (words, lines, freq, classes)
+}
+
+/// Sniffs the first two bytes of the file at `path` for the gzip magic
+/// number (`0x1f 0x8b`), so compressed input can be detected even when
+/// it lacks a `.gz` extension.
+fn sniff_gzip_magic(path: &str) -> bool {
+    let mut magic = [0u8; 2];
+    match File::open(path).and_then(|mut f| f.read_exact(&mut magic)) {
+        Ok(()) => magic == [0x1f, 0x8b],
+        Err(_) => false,
+    }
+}
+
+/// Sniffs the first two bytes of the file at `path` for a UTF-16 byte
+/// order mark, returning the matching `encoding_rs` encoding so the
+/// caller can transcode the file to UTF-8 before running the normal line
+/// loop, instead of falling back to the byte-oriented binary path.
+fn sniff_utf16_bom(path: &str) -> Option<&'static encoding_rs::Encoding> {
+    let mut bom = [0u8; 2];
+    match File::open(path).and_then(|mut f| f.read_exact(&mut bom)) {
+        Ok(()) if bom == [0xff, 0xfe] => Some(encoding_rs::UTF_16LE),
+        Ok(()) if bom == [0xfe, 0xff] => Some(encoding_rs::UTF_16BE),
+        _ => None,
+    }
+}
+
+/// Files at or under this size are read into memory once by
+/// `analyze_file_with_options` and reused for both the UTF-8 attempt and
+/// the binary fallback, instead of reopening and re-reading the file a
+/// second time if the UTF-8 attempt fails. Larger files keep streaming,
+/// since buffering them fully would trade a bounded extra disk read for
+/// an unbounded amount of memory.
+const BUFFERED_READ_CAP_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Number of times `open_for_content` has opened a file to read its
+/// analyzed content (the initial UTF-8 attempt, plus the binary fallback
+/// reopen when the file wasn't small enough to buffer). Exists so tests
+/// can confirm the fallback no longer performs a redundant open for
+/// files under `BUFFERED_READ_CAP_BYTES`.
+#[allow(dead_code)]
+static CONTENT_OPEN_COUNT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Opens `path` for reading its content, counting the call in
+/// `CONTENT_OPEN_COUNT`. A thin wrapper around `File::open` used
+/// everywhere `analyze_file_with_options` reads file content, so tests
+/// can observe how many times the file was actually opened.
+#[allow(dead_code)]
+fn open_for_content(path: &str) -> std::io::Result<File> {
+    CONTENT_OPEN_COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    File::open(path)
+}
+
+/// Returns how many times `open_for_content` has been called so far.
+/// Exposed for tests that need to confirm a file was opened only once
+/// for its content rather than being reopened on the binary fallback.
+#[allow(dead_code)]
+pub fn content_open_count() -> usize {
+    CONTENT_OPEN_COUNT.load(std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Detects the MIME type of the file at `path` from its leading magic
+/// bytes, independent of its extension, using the `infer` crate. Falls
+/// back to `"text/plain"` for content that decodes as UTF-8 but has no
+/// recognized magic number, and `"application/octet-stream"` otherwise
+/// (including read failures).
+fn detect_file_type(path: &str) -> String {
+    let mut buf = [0u8; 8192];
+    match File::open(path).and_then(|mut f| f.read(&mut buf)) {
+        Ok(n) => detect_type_from_bytes(&buf[..n]),
+        Err(_) => "application/octet-stream".to_string(),
+    }
+}
+
+/// Detects the MIME type of in-memory content already fully read into
+/// `buf`, for callers (like `analyze_reader`) with no path to re-open.
+/// Same detection rules as `detect_file_type`.
+fn detect_type_from_bytes(buf: &[u8]) -> String {
+    if let Some(kind) = infer::get(buf) {
+        kind.mime_type().to_string()
+    } else if std::str::from_utf8(buf).is_ok() {
+        "text/plain".to_string()
+    } else {
+        "application/octet-stream".to_string()
+    }
+}
+
+/// Built-in mapping from lowercase file extension to a human-readable
+/// language name, consulted by `guess_language` before falling back to a
+/// shebang sniff. Not exhaustive, just the common cases.
+const EXTENSION_LANGUAGES: &[(&str, &str)] = &[
+    ("rs", "Rust"),
+    ("py", "Python"),
+    ("js", "JavaScript"),
+    ("jsx", "JavaScript"),
+    ("mjs", "JavaScript"),
+    ("ts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("go", "Go"),
+    ("c", "C"),
+    ("h", "C"),
+    ("cpp", "C++"),
+    ("cc", "C++"),
+    ("cxx", "C++"),
+    ("hpp", "C++"),
+    ("java", "Java"),
+    ("rb", "Ruby"),
+    ("sh", "Shell"),
+    ("bash", "Shell"),
+    ("md", "Markdown"),
+    ("json", "JSON"),
+    ("toml", "TOML"),
+    ("yaml", "YAML"),
+    ("yml", "YAML"),
+    ("html", "HTML"),
+    ("htm", "HTML"),
+    ("css", "CSS"),
+    ("php", "PHP"),
+    ("pl", "Perl"),
+    ("swift", "Swift"),
+    ("kt", "Kotlin"),
+    ("cs", "C#"),
+];
+
+/// Maps a shebang interpreter name (the final path component after
+/// `#!`, or after `env` for an `#!/usr/bin/env <interpreter>` form) to a
+/// human-readable language name. `None` for an unrecognized interpreter.
+fn language_from_interpreter(interpreter: &str) -> Option<&'static str> {
+    match interpreter {
+        "python" | "python2" | "python3" => Some("Python"),
+        "bash" | "sh" | "dash" | "zsh" => Some("Shell"),
+        "node" | "nodejs" => Some("JavaScript"),
+        "ruby" => Some("Ruby"),
+        "perl" => Some("Perl"),
+        _ => None,
+    }
+}
+
+/// Guesses the programming language of `path` from its extension via
+/// `EXTENSION_LANGUAGES`, falling back to a shebang-line sniff of
+/// `first_line` (e.g. `#!/usr/bin/env python` -> `Python`) for
+/// extensionless files. Returns `None` when neither yields a match.
+fn guess_language(path: &str, first_line: &str) -> Option<String> {
+    if let Some(ext) = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+    {
+        let ext = ext.to_lowercase();
+        if let Some((_, lang)) = EXTENSION_LANGUAGES.iter().find(|(e, _)| *e == ext) {
+            return Some((*lang).to_string());
+        }
+    }
+
+    let shebang = first_line.trim_end_matches(['\n', '\r']).strip_prefix("#!")?;
+    let mut parts = shebang.split_whitespace();
+    let mut interpreter = parts.next()?;
+    if interpreter.rsplit('/').next() == Some("env") {
+        interpreter = parts.next()?;
+    }
+    let interpreter = interpreter.rsplit('/').next().unwrap_or(interpreter);
+    language_from_interpreter(interpreter).map(str::to_string)
+}
+
+/// Returns `true` if `line`'s leading run of tabs and spaces contains at
+/// least one of each, e.g. a line indented with `"\t  "` or `"  \t"`.
+fn line_has_mixed_leading_whitespace(line: &str) -> bool {
+    let mut saw_tab = false;
+    let mut saw_space = false;
+    for c in line.chars() {
+        match c {
+            '\t' => saw_tab = true,
+            ' ' => saw_space = true,
+            _ => break,
+        }
+    }
+    saw_tab && saw_space
+}
+
+/// Folds one decoded text line's terminator into the running line-ending
+/// tallies `analyze_reader` and `analyze_file_with_options` both keep, so
+/// the CRLF/LF classification rule lives in exactly one place.
+fn tally_line_ending(line: &str, lf_count: &mut usize, crlf_count: &mut usize) {
+    if line.ends_with("\r\n") {
+        *crlf_count += 1;
+    } else if line.ends_with('\n') {
+        *lf_count += 1;
+    }
+}
+
+/// Folds one decoded text line's leading whitespace into the running
+/// indentation tallies, recording both the file-wide per-line style
+/// (first character only) and whether this particular line itself mixes
+/// tabs and spaces.
+fn tally_indentation(
+    line: &str,
+    space_indent_lines: &mut usize,
+    tab_indent_lines: &mut usize,
+    single_line_mixed_indent: &mut bool,
+) {
+    match line.chars().next() {
+        Some('\t') => *tab_indent_lines += 1,
+        Some(' ') => *space_indent_lines += 1,
+        _ => {}
+    }
+    if line_has_mixed_leading_whitespace(line) {
+        *single_line_mixed_indent = true;
+    }
+}
+
+/// Counts words in one decoded text line, splitting on whitespace plus
+/// any configured extra separator characters (e.g. commas, pipes) and
+/// dropping empty tokens either way.
+fn count_words(line: &str, separators: Option<&[char]>) -> usize {
+    match separators {
+        Some(separators) => line
+            .split(|c: char| c.is_whitespace() || separators.contains(&c))
+            .filter(|token| !token.is_empty())
+            .count(),
+        None => line.split_whitespace().count(),
+    }
+}
+
+/// Folds one decoded text line's characters into the running frequency
+/// table and character-class counts.
+fn tally_chars(line: &str, freq: &mut HashMap<char, usize>, char_classes: &mut CharClassCounts) {
+    for ch in line.chars() {
+        *freq.entry(ch).or_insert(0) += 1;
+        char_classes.record(ch);
+    }
+}
+
+/// Updates the running longest-line tracking with one decoded text line
+/// (its terminator excluded from the length), keeping the first
+/// occurrence on ties. Returns the line's own trimmed character count so
+/// callers that also need it (e.g. for trailing-whitespace detection)
+/// don't have to trim the line a second time.
+fn tally_longest_line(
+    line: &str,
+    line_no: usize,
+    longest_line_len: &mut usize,
+    longest_line_no: &mut usize,
+) -> usize {
+    let line_len = line.trim_end_matches(['\n', '\r']).chars().count();
+    if line_len > *longest_line_len {
+        *longest_line_len = line_len;
+        *longest_line_no = line_no;
+    }
+    line_len
+}
+
+/// Derives the file-wide line-ending classification from the per-line
+/// CRLF/LF tallies both `analyze_reader` and `analyze_file_with_options`
+/// accumulate, so the two paths can't diverge on the classification rule.
+fn finalize_line_ending(lf_count: usize, crlf_count: usize) -> LineEnding {
+    if crlf_count > 0 && lf_count > 0 {
+        LineEnding::Mixed
+    } else if crlf_count > 0 {
+        LineEnding::CrLf
+    } else if lf_count > 0 {
+        LineEnding::Lf
+    } else {
+        LineEnding::Unknown
+    }
+}
+
+/// Derives the file-wide indentation classification from the per-line
+/// tab/space tallies both `analyze_reader` and `analyze_file_with_options`
+/// accumulate, so the two paths can't diverge on the classification rule.
+fn finalize_indentation(tab_indent_lines: usize, space_indent_lines: usize) -> Indentation {
+    if tab_indent_lines > 0 && space_indent_lines > 0 {
+        Indentation::Mixed
+    } else if tab_indent_lines > 0 {
+        Indentation::Tabs
+    } else if space_indent_lines > 0 {
+        Indentation::Spaces
+    } else {
+        Indentation::None
+    }
+}
+
+/// Counts the number of distinct invalid UTF-8 byte spans in `bytes`,
+/// scanning with `str::from_utf8` and `Utf8Error::valid_up_to`. A single
+/// `Utf8Error` only ever reports the first bad byte it hits, so after one
+/// is found, adjacent bytes that are also immediately invalid (not just
+/// the one `error_len` covers) are folded into the same run, rather than
+/// counted individually, the way a contiguous run of garbage bytes reads
+/// as one corrupted span rather than many. A trailing sequence that's
+/// merely incomplete (cut off by the end of `bytes`, e.g. a multi-byte
+/// character split across a chunk boundary) isn't counted, since it isn't
+/// actually invalid.
+fn count_invalid_utf8_sequences(bytes: &[u8]) -> usize {
+    let mut count = 0;
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let Err(e) = std::str::from_utf8(rest) else {
+            break;
+        };
+        if e.error_len().is_none() {
+            break;
+        }
+        count += 1;
+
+        let mut skip = e.valid_up_to() + 1;
+        while skip < rest.len()
+            && matches!(std::str::from_utf8(&rest[skip..]), Err(e2) if e2.valid_up_to() == 0)
+        {
+            skip += 1;
+        }
+        rest = &rest[skip..];
+    }
+    count
+}
+
+/// Computes the SHA-256 digest of `path`'s raw on-disk bytes, streaming
+/// through a fixed-size buffer rather than reading the whole file into
+/// memory, so large files don't blow up peak memory the way the buffered
+/// UTF-8 read path intentionally does for small ones. Returns `None` if
+/// the file can't be opened or read.
+pub fn hash_file(path: &str) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).ok()?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// Combines a shared `SampleLinesOptions::seed` with a file's
+/// `sequence_id` via a fixed-point multiplicative mix, producing a
+/// distinct per-file RNG seed that's nonetheless fully determined by the
+/// pair, so sampling results don't depend on the order threads actually
+/// process files in.
+fn seed_for_sequence(seed: u64, sequence_id: u64) -> u64 {
+    seed ^ sequence_id.wrapping_mul(0x9E3779B97F4A7C15)
+}
+
+/// A tiny deterministic PRNG (xorshift64) used to pick reservoir-sampled
+/// lines. Not cryptographically secure and not intended to be: the only
+/// requirement is that the same seed always produces the same sequence,
+/// so sampled previews are reproducible across runs and tests.
+struct Xorshift64 {
+    state: u64,
+}
+
+impl Xorshift64 {
+    /// Seeds the generator, substituting `1` for a zero seed since an
+    /// all-zero xorshift state never advances.
+    fn new(seed: u64) -> Self {
+        Xorshift64 {
+            state: if seed == 0 { 1 } else { seed },
+        }
+    }
+
+    /// Returns a value uniformly distributed over `0..bound`. `bound` must
+    /// be nonzero; the modulo-reduction bias is negligible for the small
+    /// bounds reservoir sampling uses.
+    fn below(&mut self, bound: u64) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state % bound
+    }
+}
+
+/// Scans `path` line-by-line (transparently decompressing gzip input, like
+/// `analyze_file`) for lines matching `regex`, returning the match count
+/// and the matching lines themselves (1-based line number, trimmed text)
+/// for grep-like display. Read failures are treated as zero matches
+/// rather than surfaced here, since `analyze_file` already reports them.
+pub fn scan_regex_matches(path: &str, regex: &Regex) -> (usize, Vec<(usize, String)>) {
+    let mut match_count = 0usize;
+    let mut matches = Vec::new();
+
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return (match_count, matches),
+    };
+
+    let is_gzip = path.ends_with(".gz") || sniff_gzip_magic(path);
+    let mut reader: Box<dyn BufRead> = if is_gzip {
+        Box::new(BufReader::new(GzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    let mut line_buf = String::new();
+    let mut line_no = 0usize;
+
+    loop {
+        line_buf.clear();
+        match reader.read_line(&mut line_buf) {
+            Ok(0) => break,
+            Ok(_) => {
+                line_no += 1;
+                if regex.is_match(&line_buf) {
+                    match_count += 1;
+                    matches.push((line_no, line_buf.trim_end_matches(['\n', '\r']).to_string()));
+                }
+            }
+            Err(_) => break,
+        }
+    }
+
+    (match_count, matches)
+}
+
+/// Scans `path` line-by-line (transparently decompressing gzip input, like
+/// `analyze_file`) and builds a per-word occurrence count, normalized the
+/// same way as `AnalyzeOptions::track_word_frequencies`: lowercased, with
+/// surrounding punctuation trimmed, dropping empty tokens. A separate pass
+/// from the main analysis loop (like `scan_regex_matches`/`hash_file`), so
+/// it's only paid for when word-frequency aggregation (e.g. `--top-words`)
+/// is actually requested. Read failures return an empty map rather than
+/// being surfaced here, since `analyze_file` already reports them.
+pub fn word_frequencies(path: &str) -> HashMap<String, usize> {
+    let mut freq: HashMap<String, usize> = HashMap::new();
+
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return freq,
+    };
+
+    let is_gzip = path.ends_with(".gz") || sniff_gzip_magic(path);
+    let mut reader: Box<dyn BufRead> = if is_gzip {
+        Box::new(BufReader::new(GzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    let mut line_buf = String::new();
+    loop {
+        line_buf.clear();
+        match reader.read_line(&mut line_buf) {
+            Ok(0) => break,
+            Ok(_) => {
+                for word in line_buf.split_whitespace() {
+                    let normalized = word
+                        .trim_matches(|c: char| !c.is_alphanumeric())
+                        .to_lowercase();
+                    if !normalized.is_empty() {
+                        *freq.entry(normalized).or_insert(0) += 1;
+                    }
+                }
+            }
+            Err(_) => break,
+        }
+    }
+
+    freq
+}
+
+/// A single named metric produced by an `Analyzer`, stored as a JSON value
+/// so analyzers can report whatever shape of result fits their metric
+/// (a count, a list of matches, a nested breakdown, etc).
+#[allow(dead_code)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsFragment {
+    pub name: String,
+    pub value: serde_json::Value,
+}
+
+/// A pluggable per-file metric collector, run alongside other analyzers
+/// over a single read pass of a file. Implementations receive each line
+/// for UTF-8 input via `on_line`, or raw byte chunks via `on_bytes` on the
+/// binary fallback path, and report their result from `finish`.
+///
+/// See `analyze_file_with_analyzers` for how a list of analyzers is run,
+/// and `CoreAnalyzer` for the stock word/line/char-frequency metrics.
+#[allow(dead_code)]
+pub trait Analyzer {
+    /// Called once per line for files read as UTF-8 text.
+    fn on_line(&mut self, _line: &str) {}
+
+    /// Called once per chunk of raw bytes, used for the binary fallback
+    /// path when a file isn't valid UTF-8.
+    fn on_bytes(&mut self, _bytes: &[u8]) {}
+
+    /// Consumes the analyzer and returns its collected result.
+    fn finish(self: Box<Self>) -> StatsFragment;
+}
+
+/// The stock `Analyzer` implementation providing the same word count,
+/// line count, and character-frequency metrics as `analyze_file`.
+#[allow(dead_code)]
+#[derive(Debug, Default)]
+pub struct CoreAnalyzer {
+    word_count: usize,
+    line_count: usize,
+    char_frequencies: HashMap<char, usize>,
+}
+
+impl CoreAnalyzer {
+    #[allow(dead_code)]
+    pub fn new() -> Self {
+        CoreAnalyzer::default()
+    }
+}
+
+impl Analyzer for CoreAnalyzer {
+    fn on_line(&mut self, line: &str) {
+        self.line_count += 1;
+        self.word_count += line.split_whitespace().count();
+        for ch in line.chars() {
+            *self.char_frequencies.entry(ch).or_insert(0) += 1;
+        }
+    }
+
+    fn on_bytes(&mut self, bytes: &[u8]) {
+        self.line_count += bytes.iter().filter(|&&b| b == b'\n').count();
+        self.word_count += bytes
+            .split(|&c| c == b' ' || c == b'\n' || c == b'\t' || c == b'\r')
+            .filter(|slice| !slice.is_empty())
+            .count();
+        for &b in bytes {
+            *self.char_frequencies.entry(b as char).or_insert(0) += 1;
+        }
+    }
+
+    fn finish(self: Box<Self>) -> StatsFragment {
+        let char_frequencies: HashMap<String, usize> = self
+            .char_frequencies
+            .iter()
+            .map(|(ch, count)| (ch.to_string(), *count))
+            .collect();
+
+        StatsFragment {
+            name: "core".to_string(),
+            value: serde_json::json!({
+                "word_count": self.word_count,
+                "line_count": self.line_count,
+                "char_frequencies": char_frequencies,
+            }),
+        }
+    }
+}
+
+/// Analyzes a file at the given path by running `analyzers` over it in a
+/// single read pass, so adding a custom metric (e.g. a regex matcher)
+/// doesn't require editing `analyze_file_with_options`. Include a
+/// `CoreAnalyzer` in `analyzers` to also populate the returned
+/// `FileAnalysis`'s word/line/char-frequency stats from its fragment.
+///
+/// Returns the resulting `FileAnalysis` alongside every analyzer's
+/// `StatsFragment`, in the order the analyzers were given.
+#[allow(dead_code)]
+pub fn analyze_file_with_analyzers(
+    path: &str,
+    mut analyzers: Vec<Box<dyn Analyzer>>,
+) -> (FileAnalysis, Vec<StatsFragment>) {
+    let start = Instant::now();
+    let mut stats = FileStats::new();
+    let mut errors = Vec::new();
+
+    match std::fs::metadata(path) {
+        Ok(metadata) => {
+            stats.size_bytes = metadata.len();
+            stats.is_empty = metadata.len() == 0;
+        }
+        Err(e) => errors.push(ProcessingError {
+            filename: path.to_string(),
+            operation: "metadata".to_string(),
+            message: format!("{:?}", e),
+        }),
+    }
+
+    let file = match File::open(path) {
+        Ok(f) => f,
+        Err(e) => {
+            errors.push(ProcessingError {
+                filename: path.to_string(),
+                operation: "open".to_string(),
+                message: format!("{:?}", e),
+            });
+            let analysis = FileAnalysis {
+                filename: path.to_string(),
+                stats,
+                errors,
+                processing_time: start.elapsed(),
+                cpu_time: None,
+                sample_lines: Vec::new(),
+            };
+            let fragments = analyzers.into_iter().map(|a| a.finish()).collect();
+            return (analysis, fragments);
+        }
+    };
+
+    let is_gzip = path.ends_with(".gz") || sniff_gzip_magic(path);
+    let mut reader: Box<dyn BufRead> = if is_gzip {
+        Box::new(BufReader::new(GzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    let mut line_buf = String::new();
+    let mut utf8_ok = true;
+
+    loop {
+        line_buf.clear();
+        match reader.read_line(&mut line_buf) {
+            Ok(0) => break,
+            Ok(_) => {
+                for analyzer in analyzers.iter_mut() {
+                    analyzer.on_line(&line_buf);
+                }
+            }
+            Err(e) => {
+                utf8_ok = false;
+                errors.push(ProcessingError {
+                    filename: path.to_string(),
+                    operation: "read_line".to_string(),
+                    message: format!("{:?}", e),
+                });
+                break;
+            }
+        }
+    }
+
+    if !utf8_ok {
+        let raw: Option<Box<dyn Read>> = match File::open(path) {
+            Ok(f) if is_gzip => Some(Box::new(GzDecoder::new(f))),
+            Ok(f) => Some(Box::new(f)),
+            Err(_) => None,
+        };
+
+        if let Some(mut f) = raw {
+            let mut buf = [0u8; 8 * 1024];
+            loop {
+                match f.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        for analyzer in analyzers.iter_mut() {
+                            analyzer.on_bytes(&buf[..n]);
+                        }
+                    }
+                    Err(e2) => {
+                        errors.push(ProcessingError {
+                            filename: path.to_string(),
+                            operation: "read_raw".to_string(),
+                            message: format!("{:?}", e2),
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    let fragments: Vec<StatsFragment> = analyzers.into_iter().map(|a| a.finish()).collect();
+
+    if let Some(core) = fragments.iter().find(|f| f.name == "core") {
+        if let Some(word_count) = core.value.get("word_count").and_then(|v| v.as_u64()) {
+            stats.word_count = word_count as usize;
+        }
+        if let Some(line_count) = core.value.get("line_count").and_then(|v| v.as_u64()) {
+            stats.line_count = line_count as usize;
+        }
+        if let Some(freq) = core.value.get("char_frequencies").and_then(|v| v.as_object()) {
+            for (key, count) in freq {
+                if let (Some(ch), Some(count)) = (key.chars().next(), count.as_u64()) {
+                    stats.char_frequencies.insert(ch, count as usize);
+                }
+            }
+        }
+    }
+
+    stats.fell_back_to_binary = !utf8_ok;
+
+    let analysis = FileAnalysis {
+        filename: path.to_string(),
+        stats,
+        errors,
+        processing_time: start.elapsed(),
+        cpu_time: None,
+        sample_lines: Vec::new(),
+    };
+
+    (analysis, fragments)
 }
@@ -0,0 +1,79 @@
+// cache.rs
+//
+// This module implements an on-disk cache mapping a file's path, modification
+// time, and size to its previously computed `FileAnalysis`, so re-running the
+// tool on an unchanged directory can skip re-analyzing unchanged files.
+
+use crate::analyzer::FileAnalysis;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::UNIX_EPOCH;
+
+/// A single cached entry, keyed by the file's mtime and size at the time
+/// it was analyzed. The entry is invalidated when either differs on a
+/// later lookup.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CacheEntry {
+    mtime_secs: u64,
+    size: u64,
+    analysis: FileAnalysis,
+}
+
+/// An on-disk cache of file analyses, keyed by path.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct Cache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl Cache {
+    /// Loads a cache from `path`, or returns an empty cache if the file
+    /// doesn't exist or can't be parsed.
+    pub fn load(path: &str) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Persists the cache to `path` as JSON.
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        let json = serde_json::to_string(self).unwrap_or_default();
+        std::fs::write(path, json)
+    }
+
+    /// Looks up a cached analysis for `path`, returning it only if the
+    /// provided mtime and size still match what was cached.
+    pub fn get(&self, path: &str, mtime_secs: u64, size: u64) -> Option<&FileAnalysis> {
+        let entry = self.entries.get(path)?;
+        if entry.mtime_secs == mtime_secs && entry.size == size {
+            Some(&entry.analysis)
+        } else {
+            None
+        }
+    }
+
+    /// Inserts or replaces the cached analysis for `path`.
+    pub fn insert(&mut self, path: String, mtime_secs: u64, size: u64, analysis: FileAnalysis) {
+        self.entries.insert(
+            path,
+            CacheEntry {
+                mtime_secs,
+                size,
+                analysis,
+            },
+        );
+    }
+}
+
+/// Reads a file's modification time (as seconds since the Unix epoch) and
+/// size, returning `None` if metadata can't be read.
+pub fn file_cache_key(path: &str) -> Option<(u64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((mtime, metadata.len()))
+}
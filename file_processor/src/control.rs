@@ -0,0 +1,81 @@
+// control.rs
+//
+// Line-protocol control interface for `FileProcessor`: a running batch
+// can be driven from a GUI or another process by sending one command per
+// line (`pause`, `resume`, `cancel`, `status`) and reading back a JSON
+// line after each one, without embedding a larger RPC framework.
+
+use crate::processor::{CancellationToken, FileProcessor};
+use serde::Serialize;
+
+/// A parsed control-interface command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    Cancel,
+    Status,
+}
+
+/// Parses a single line of input into a `ControlCommand`. Matching is
+/// case-insensitive and ignores leading/trailing whitespace. Returns
+/// `None` for anything else, so a caller can skip or report malformed
+/// input rather than the whole control loop erroring out over a typo.
+pub fn parse_control_command(line: &str) -> Option<ControlCommand> {
+    match line.trim().to_ascii_lowercase().as_str() {
+        "pause" => Some(ControlCommand::Pause),
+        "resume" => Some(ControlCommand::Resume),
+        "cancel" => Some(ControlCommand::Cancel),
+        "status" => Some(ControlCommand::Status),
+        _ => None,
+    }
+}
+
+/// Response emitted as a JSON line after applying a `ControlCommand`,
+/// reporting the processor's state immediately after the command took
+/// effect.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ControlResponse {
+    pub command: &'static str,
+    pub paused: bool,
+    pub cancelled: bool,
+    pub completed: usize,
+    pub total: usize,
+    pub bytes: usize,
+}
+
+/// Applies `command` to `processor`/`token` and returns the resulting
+/// `ControlResponse`. `pause`/`resume` gate the dispatcher via
+/// `FileProcessor`'s internal `Condvar`; `cancel` sets `token`'s
+/// cancellation flag; `status` makes no change and just reports state.
+pub fn apply_control_command(
+    command: ControlCommand,
+    processor: &FileProcessor,
+    token: &CancellationToken,
+) -> ControlResponse {
+    let command_name = match command {
+        ControlCommand::Pause => {
+            processor.pause();
+            "pause"
+        }
+        ControlCommand::Resume => {
+            processor.resume();
+            "resume"
+        }
+        ControlCommand::Cancel => {
+            token.cancel();
+            "cancel"
+        }
+        ControlCommand::Status => "status",
+    };
+
+    let status = processor.status();
+    ControlResponse {
+        command: command_name,
+        paused: processor.is_paused(),
+        cancelled: token.is_cancelled(),
+        completed: status.completed,
+        total: status.total,
+        bytes: status.bytes,
+    }
+}
@@ -0,0 +1,13 @@
+// lib.rs
+//
+// Library crate for file_processor: file analysis, multithreaded
+// dispatch, and on-disk progress caching. Kept independent of the CLI
+// front-end in main.rs so the processing pipeline can be embedded in
+// other programs and exercised directly by integration tests under
+// `tests/`, without going through the command-line binary.
+
+pub mod analyzer;
+mod cache;
+pub mod control;
+pub mod processor;
+pub mod thread_pool;
@@ -7,91 +7,896 @@
 // 3. Receiving and handling progress updates
 // 4. Displaying final statistics to the user
 
-mod analyzer;
-mod processor;
-mod thread_pool;
+use file_processor::{analyzer, control, processor, thread_pool};
 
-use analyzer::FileAnalysis;
-use processor::{FileProcessor, ProgressMessage};
+#[cfg(test)]
+mod tests;
+
+use analyzer::{FileAnalysis, ProcessingError};
+use processor::{FileProcessor, PathStyle, ProgressMessage, SortOrder};
+use regex::Regex;
+use serde::Serialize;
 use std::env;
 use std::sync::mpsc::RecvTimeoutError;
-use std::time::Duration;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 use thread_pool::ThreadPool;
 
 fn main() {
+    if let Err(e) = main_inner(env::args().collect()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Parses arguments, drives `run` to completion, and prints the live
+/// progress and final summary. Kept separate from `run` so the processing
+/// pipeline itself stays embeddable and testable without touching stdout.
+fn main_inner(args: Vec<String>) -> Result<(), String> {
+    let cli = parse_args(args)?;
+    let quiet = cli.quiet;
+    let jsonl = cli.jsonl;
+    let verbose = cli.verbose;
+    let verbose_char_table = cli.verbose_char_table;
+    let count_chars = cli.count_chars.clone();
+    let include_empty_dirs = cli.include_empty_dirs;
+    let dedup = cli.dedup;
+    let tree = cli.tree;
+    let top_words = cli.top_words;
+    let output_path = cli.output_path.clone();
+    let metrics_path = cli.metrics_path.clone();
+    let baseline_path = cli.baseline_path.clone();
+    let record_session_path = cli.record_session_path.clone();
+    let num_threads = cli.config.num_threads;
+
+    // Recorded in dispatch order (the order `FileStarted` arrives, which is
+    // deterministic) rather than completion order (`summary.analyses`,
+    // which depends on worker scheduling), so a `--replay` of this session
+    // reproduces the same dispatch sequence regardless of timing.
+    let mut dispatch_log: Vec<SessionEntry> = Vec::new();
+
+    let summary = run(cli.config, |msg| match msg {
+        // A file has started processing
+        ProgressMessage::FileStarted(name) => {
+            if !quiet {
+                println!("Started: {}", name);
+            }
+            dispatch_log.push(SessionEntry {
+                path: name.clone(),
+                status: "pending".to_string(),
+            });
+        }
+
+        // A file completed successfully
+        ProgressMessage::FileCompleted(analysis) => {
+            if jsonl {
+                print_jsonl_completed(analysis);
+            } else if verbose {
+                handle_completed_verbose(analysis, verbose_char_table);
+            } else if !quiet {
+                handle_completed(analysis);
+            }
+            if !jsonl && !quiet && !count_chars.is_empty() {
+                print_char_counts(analysis, &count_chars);
+            }
+            if let Some(entry) = dispatch_log
+                .iter_mut()
+                .rev()
+                .find(|e| e.path == analysis.filename && e.status == "pending")
+            {
+                entry.status = "ok".to_string();
+            }
+        }
+
+        // A file failed during processing
+        ProgressMessage::FileFailed(name, err) => {
+            if jsonl {
+                print_jsonl_failed(name, err);
+            } else {
+                // Errors always go to stderr, even in quiet mode.
+                eprintln!("Failed {}: {} - {}", name, err.operation, err.message);
+            }
+            if let Some(entry) = dispatch_log
+                .iter_mut()
+                .rev()
+                .find(|e| &e.path == name && e.status == "pending")
+            {
+                entry.status = "error".to_string();
+            }
+        }
+
+        // A file completed but recorded non-fatal errors along the way
+        ProgressMessage::FileWarning(name, errors) => {
+            // Warnings always go to stderr, even in quiet mode.
+            for err in errors {
+                eprintln!("Warning {}: {} - {}", name, err.operation, err.message);
+            }
+        }
+
+        // Periodic update containing total and completed counts
+        ProgressMessage::OverallProgress { completed: c, total: t } => {
+            if !quiet {
+                println!("Progress: {}/{}", c, t);
+            }
+        }
+    })?;
+
     // ----------------------------------------
-    // Parse command-line arguments
+    // Final summary output
     // ----------------------------------------
+    if tree {
+        println!("Tree:");
+        print!("{}", render_tree(&build_tree(&summary.analyses), ".", 0));
+    }
+
+    // Per-directory subtotals, grouped by each file's parent directory,
+    // printed before the grand total.
+    for (dir, dir_summary) in group_by_directory(&summary.analyses) {
+        println!(
+            "  {}: files={}, words={}, lines={}, bytes={}",
+            dir, dir_summary.files, dir_summary.words, dir_summary.lines, dir_summary.bytes
+        );
+    }
+
+    // Per-extension subtotals, sorted by total bytes (largest first).
+    println!("By extension:");
+    for (ext, ext_summary) in group_by_extension(&summary.analyses) {
+        println!(
+            "  {}: files={}, words={}, lines={}, bytes={}",
+            ext, ext_summary.files, ext_summary.words, ext_summary.lines, ext_summary.bytes
+        );
+    }
+
+    println!("By language:");
+    for (language, files) in group_by_language(&summary.analyses) {
+        println!("  {}: files={}", language, files);
+    }
+
+    let mut slowest = TopN::new(5);
+    let mut largest = TopN::new(5);
+    for analysis in &summary.analyses {
+        slowest.record(analysis.processing_time, analysis.filename.clone());
+        largest.record(analysis.stats.size_bytes, analysis.filename.clone());
+    }
+
+    println!("Top 5 slowest files:");
+    for (time, name) in slowest.into_sorted() {
+        println!("  {}: {:?}", name, time);
+    }
+
+    println!("Top 5 largest files:");
+    for (bytes, name) in largest.into_sorted() {
+        println!("  {}: {} bytes", name, bytes);
+    }
+
+    println!("Processing time histogram:");
+    for (label, count) in processing_time_histogram(&summary.analyses) {
+        println!("  {}: {}", label, count);
+    }
+
+    println!(
+        "Queue depth: peak={}, average={:.2}",
+        summary.peak_queue_depth, summary.average_queue_depth
+    );
+    if summary.peak_queue_depth > num_threads {
+        println!("queue was saturated; consider more threads");
+    }
+    if summary.budget_exceeded {
+        println!("stopped early: reached --max-total-bytes budget");
+    }
+    let max_errors_exceeded = summary.max_errors_exceeded;
+    if max_errors_exceeded {
+        println!("stopped early: reached --max-errors limit");
+    }
+    if summary.runtime_exceeded {
+        println!("stopped early: reached --max-runtime limit");
+    }
+
+    let empty_files = summary
+        .analyses
+        .iter()
+        .filter(|a| a.stats.is_empty)
+        .count();
+
+    let files_with_trailing_ws = summary
+        .analyses
+        .iter()
+        .filter(|a| a.stats.lines_with_trailing_ws > 0)
+        .count();
+
+    let binary_files = summary
+        .analyses
+        .iter()
+        .filter(|a| a.stats.fell_back_to_binary)
+        .count();
+    if binary_files > 0 {
+        println!("{} files analyzed as binary", binary_files);
+    }
+
+    if !count_chars.is_empty() {
+        println!("Char counts across all files:");
+        for (c, count) in aggregate_char_counts(&summary.analyses, &count_chars) {
+            println!("  {}: {}", escape_char(c), count);
+        }
+    }
+
+    if include_empty_dirs && !summary.empty_dirs.is_empty() {
+        println!("Empty directories (no matching files):");
+        for dir in &summary.empty_dirs {
+            println!("  {}", dir);
+        }
+    }
+
+    if dedup {
+        let duplicate_groups = find_duplicate_groups(&summary.analyses);
+        if !duplicate_groups.is_empty() {
+            println!("Duplicate files (identical content):");
+            for (hash, paths) in &duplicate_groups {
+                println!("  {}:", hash);
+                for path in paths {
+                    println!("    {}", path);
+                }
+            }
+        }
+    }
+
+    if let Some(n) = top_words {
+        println!("Top {} words across all files:", n);
+        for (word, count) in aggregate_top_words(&summary.analyses, n) {
+            println!("  {}: {}", word, count);
+        }
+    }
+
+    if let Some(path) = baseline_path {
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read --baseline {:?}: {}", path, e))?;
+        let baseline: Report = serde_json::from_str(&contents)
+            .map_err(|e| format!("failed to parse --baseline {:?}: {}", path, e))?;
+        let diff = compute_baseline_diff(&baseline.analyses, &summary.analyses);
+
+        println!("Baseline diff against {:?}:", path);
+        if !diff.added.is_empty() {
+            println!("  Added files:");
+            for filename in &diff.added {
+                println!("    {}", filename);
+            }
+        }
+        if !diff.removed.is_empty() {
+            println!("  Removed files:");
+            for filename in &diff.removed {
+                println!("    {}", filename);
+            }
+        }
+        for delta in &diff.changed {
+            if delta.word_delta != 0 || delta.line_delta != 0 || delta.byte_delta != 0 {
+                println!(
+                    "  {}: words={:+}, lines={:+}, bytes={:+}",
+                    delta.filename, delta.word_delta, delta.line_delta, delta.byte_delta
+                );
+            }
+        }
+        println!(
+            "  Total: words={:+}, lines={:+}, bytes={:+}",
+            diff.total_word_delta, diff.total_line_delta, diff.total_byte_delta
+        );
+    }
+
+    let (bytes_per_sec, files_per_sec) =
+        compute_throughput(summary.total_bytes, summary.total_files, summary.elapsed);
+
+    println!(
+        "Done. Total bytes processed: {}. Total words: {}. Total lines: {}. Empty files: {}. Files with trailing whitespace: {}. Errors: {}",
+        summary.total_bytes,
+        summary.total_words,
+        summary.total_lines,
+        empty_files,
+        files_with_trailing_ws,
+        summary.total_errors
+    );
+    println!(
+        "Elapsed: {:?}. Throughput: {:.2} bytes/sec, {:.2} files/sec",
+        summary.elapsed, bytes_per_sec, files_per_sec
+    );
+
+    // Writing a JSON report is independent of the stdout sink above: it
+    // runs regardless of `--format`/`--quiet`/`--verbose`, so live
+    // progress on stdout and a persisted report file can be requested
+    // together in the same run.
+    if let Some(path) = output_path {
+        let report = Report {
+            total_files: summary.total_files,
+            total_bytes: summary.total_bytes,
+            empty_files,
+            elapsed_ms: summary.elapsed.as_millis() as u64,
+            analyses: summary.analyses,
+        };
+        let json = serde_json::to_string(&report)
+            .map_err(|e| format!("failed to serialize report: {}", e))?;
+        std::fs::write(&path, json).map_err(|e| format!("failed to write --output {:?}: {}", path, e))?;
+    }
+
+    // Writing Prometheus metrics is independent of the stdout sink and the
+    // `--output` JSON report above, so all three can be requested together.
+    if let Some(path) = metrics_path {
+        let metrics = render_prometheus_metrics(
+            summary.total_files,
+            summary.total_bytes,
+            summary.total_errors,
+            summary.elapsed,
+        );
+        std::fs::write(&path, metrics)
+            .map_err(|e| format!("failed to write --metrics {:?}: {}", path, e))?;
+    }
+
+    // Writing a session file is likewise independent of the other sinks,
+    // so a run can be recorded for later `--replay` alongside any other
+    // output a caller asked for.
+    if let Some(path) = record_session_path {
+        let session = Session {
+            entries: dispatch_log,
+        };
+        let json = serde_json::to_string(&session)
+            .map_err(|e| format!("failed to serialize session: {}", e))?;
+        std::fs::write(&path, json)
+            .map_err(|e| format!("failed to write --record-session {:?}: {}", path, e))?;
+    }
+
+    // Unlike `--fail-fast` (which aborts immediately, before a summary is
+    // ever printed), `--max-errors` lets the summary above print normally
+    // and only then reports failure, so a caller still gets the full
+    // picture of what happened before the run was cut short.
+    if max_errors_exceeded {
+        return Err("aborted: reached --max-errors limit".to_string());
+    }
+
+    Ok(())
+}
+
+/// Renders aggregate run totals as Prometheus text exposition format, for
+/// `--metrics <file>`: one `# HELP`/`# TYPE` pair plus a value line per
+/// counter/gauge, so the output can be scraped directly or fed to
+/// `promtool`/a push-gateway without further translation.
+fn render_prometheus_metrics(
+    total_files: usize,
+    total_bytes: usize,
+    total_errors: usize,
+    elapsed: Duration,
+) -> String {
+    format!(
+        "# HELP fileproc_files_total Total number of files processed.\n\
+         # TYPE fileproc_files_total counter\n\
+         fileproc_files_total {total_files}\n\
+         # HELP fileproc_bytes_total Total number of bytes processed.\n\
+         # TYPE fileproc_bytes_total counter\n\
+         fileproc_bytes_total {total_bytes}\n\
+         # HELP fileproc_errors_total Total number of files that failed to process.\n\
+         # TYPE fileproc_errors_total counter\n\
+         fileproc_errors_total {total_errors}\n\
+         # HELP fileproc_duration_seconds Total wall-clock duration of the run, in seconds.\n\
+         # TYPE fileproc_duration_seconds gauge\n\
+         fileproc_duration_seconds {duration_seconds}\n",
+        total_files = total_files,
+        total_bytes = total_bytes,
+        total_errors = total_errors,
+        duration_seconds = elapsed.as_secs_f64(),
+    )
+}
+
+/// Parsed command-line arguments, split into the `RunConfig` the pipeline
+/// itself needs and the presentation flags that only affect how `main`
+/// displays the result.
+struct Cli {
+    config: RunConfig,
+    quiet: bool,
+    verbose: bool,
+    verbose_char_table: bool,
+    count_chars: Vec<char>,
+    include_empty_dirs: bool,
+    dedup: bool,
+    top_words: Option<usize>,
+    jsonl: bool,
+    output_path: Option<String>,
+    metrics_path: Option<String>,
+    baseline_path: Option<String>,
+    tree: bool,
+    record_session_path: Option<String>,
+}
+
+/// Configuration for a single run of the processing pipeline. Deliberately
+/// free of anything related to how results get displayed, so `run` can be
+/// called from tests or embedded in another program without pulling in
+/// stdout formatting.
+struct RunConfig {
+    num_threads: usize,
+    dirs: Vec<String>,
+    max_files_per_sec: Option<f64>,
+    progress_interval: Option<Duration>,
+    cache_path: Option<String>,
+    match_pattern: Option<Regex>,
+    max_depth: Option<usize>,
+    exclude_hidden: bool,
+    watch: bool,
+    strict: bool,
+    strict_roots: bool,
+    range: Option<(u64, u64)>,
+    files_only: bool,
+    since: Option<std::time::SystemTime>,
+    control: bool,
+    fail_fast: bool,
+    sort: Option<SortOrder>,
+    max_total_bytes: Option<u64>,
+    dedup: bool,
+    top_words: Option<usize>,
+    path_style: Option<PathStyle>,
+    max_errors: Option<usize>,
+    lossy: bool,
+    max_runtime: Option<Duration>,
+}
+
+/// Aggregate totals and per-file results produced by a single `run` of the
+/// pipeline. Returned rather than printed so the pipeline is testable and
+/// embeddable without touching stdout.
+struct RunSummary {
+    total_files: usize,
+    total_words: usize,
+    total_lines: usize,
+    total_bytes: usize,
+    total_errors: usize,
+    elapsed: Duration,
+    analyses: Vec<FileAnalysis>,
+    peak_queue_depth: usize,
+    average_queue_depth: f64,
+    budget_exceeded: bool,
+    max_errors_exceeded: bool,
+    runtime_exceeded: bool,
+    empty_dirs: Vec<String>,
+}
+
+/// Parses `args` into a `Cli`. Returns an error message (rather than
+/// panicking or calling `std::process::exit` directly) so bad input is
+/// scriptable: a caller gets a clean message on stderr and a nonzero exit
+/// code instead of a panic/backtrace.
+fn parse_args(mut args: Vec<String>) -> Result<Cli, String> {
     // Expected usage:
-    //   <program> <num_threads> <dir1> [dir2 ...]
-    let args: Vec<String> = env::args().collect();
+    //   <program> [--max-files-per-sec <rate>] [--progress-interval-ms <ms>] [--cache <path>] [--match <regex>] [--max-depth <n>] [--format text|jsonl] [--output <file>] [--exclude-hidden] [--watch] [--files0-from <file>|-] [--strict] [--strict-roots] [--range START:END] [--files-only] [--since <rfc3339>] [--control] [--fail-fast] [--sort name|size|mtime] [--reproducible] [--max-total-bytes <n>] [--quiet] [--verbose] [--verbose-char-table] [--count-char <c>] [--include-empty-dirs] [--dedup] [--top-words <n>] [--path-style <abs|rel|base>] [--metrics <file>] [--baseline <file>] [--max-errors <n>] [--max-runtime <duration>] [--tree] [--lossy] [--record-session <file>] [--replay <file>] <num_threads|auto> [dir1 dir2 ...]
+    let max_files_per_sec = take_flag_value(&mut args, "--max-files-per-sec")
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("invalid --max-files-per-sec {:?}", v))
+        })
+        .transpose()?;
+    let progress_interval_ms = take_flag_value(&mut args, "--progress-interval-ms")
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("invalid --progress-interval-ms {:?}", v))
+        })
+        .transpose()?;
+    let cache_path = take_flag_value(&mut args, "--cache");
+    let max_depth = take_flag_value(&mut args, "--max-depth")
+        .map(|v| v.parse().map_err(|_| format!("invalid --max-depth {:?}", v)))
+        .transpose()?;
+    let match_pattern = take_flag_value(&mut args, "--match")
+        .map(|pattern| {
+            Regex::new(&pattern).map_err(|e| format!("Invalid --match regex {:?}: {}", pattern, e))
+        })
+        .transpose()?;
+    let format = take_flag_value(&mut args, "--format").unwrap_or_else(|| "text".to_string());
+    let jsonl = match format.as_str() {
+        "text" => false,
+        "jsonl" => true,
+        other => {
+            return Err(format!(
+                "Invalid --format {:?}: expected \"text\" or \"jsonl\"",
+                other
+            ))
+        }
+    };
+    let quiet = take_flag(&mut args, "--quiet");
+    let verbose = take_flag(&mut args, "--verbose");
+    let verbose_char_table = take_flag(&mut args, "--verbose-char-table");
+    let count_chars = take_flag_values(&mut args, "--count-char")
+        .into_iter()
+        .map(|v| parse_count_char(&v))
+        .collect::<Result<Vec<char>, String>>()?;
+    let include_empty_dirs = take_flag(&mut args, "--include-empty-dirs");
+    let dedup = take_flag(&mut args, "--dedup");
+    let tree = take_flag(&mut args, "--tree");
+    let lossy = take_flag(&mut args, "--lossy");
+    let top_words = take_flag_value(&mut args, "--top-words")
+        .map(|v| v.parse().map_err(|_| format!("invalid --top-words {:?}", v)))
+        .transpose()?;
+    let exclude_hidden = take_flag(&mut args, "--exclude-hidden");
+    let output_path = take_flag_value(&mut args, "--output");
+    let metrics_path = take_flag_value(&mut args, "--metrics");
+    let baseline_path = take_flag_value(&mut args, "--baseline");
+    let record_session_path = take_flag_value(&mut args, "--record-session");
+    let replay_path = take_flag_value(&mut args, "--replay");
+    let watch = take_flag(&mut args, "--watch");
+    let files0_from = take_flag_value(&mut args, "--files0-from");
+    let strict = take_flag(&mut args, "--strict");
+    let strict_roots = take_flag(&mut args, "--strict-roots");
+    let range = take_flag_value(&mut args, "--range")
+        .map(|v| parse_range(&v))
+        .transpose()?;
+    let files_only = take_flag(&mut args, "--files-only");
+    let control = take_flag(&mut args, "--control");
+    let fail_fast = take_flag(&mut args, "--fail-fast");
+    let reproducible = take_flag(&mut args, "--reproducible");
+    let mut sort = take_flag_value(&mut args, "--sort")
+        .map(|v| parse_sort_order(&v))
+        .transpose()?
+        .or(if reproducible { Some(SortOrder::Name) } else { None });
+    let path_style = take_flag_value(&mut args, "--path-style")
+        .map(|v| parse_path_style(&v))
+        .transpose()?;
+    let max_total_bytes = take_flag_value(&mut args, "--max-total-bytes")
+        .map(|v| {
+            v.parse()
+                .map_err(|_| format!("invalid --max-total-bytes {:?}", v))
+        })
+        .transpose()?;
+    let max_errors = take_flag_value(&mut args, "--max-errors")
+        .map(|v| v.parse().map_err(|_| format!("invalid --max-errors {:?}", v)))
+        .transpose()?;
+    let since = take_flag_value(&mut args, "--since")
+        .map(|v| {
+            humantime::parse_rfc3339(&v).map_err(|e| format!("invalid --since {:?}: {}", v, e))
+        })
+        .transpose()?;
+    let max_runtime = take_flag_value(&mut args, "--max-runtime")
+        .map(|v| {
+            humantime::parse_duration(&v)
+                .map_err(|e| format!("invalid --max-runtime {:?}: {}", v, e))
+        })
+        .transpose()?;
 
-    // Ensure minimum required arguments are provided
-    if args.len() < 3 {
-        eprintln!("Usage: {} <num_threads> <dir1> [dir2 ...]", args[0]);
-        std::process::exit(1);
+    // Ensure minimum required arguments are provided. `--files0-from` and
+    // `--replay` can each supply every path, so positional directories
+    // become optional when either is set.
+    if args.len() < 2 || (args.len() < 3 && files0_from.is_none() && replay_path.is_none()) {
+        return Err(format!(
+            "Usage: {} [--max-files-per-sec <rate>] [--progress-interval-ms <ms>] [--cache <path>] [--match <regex>] [--max-depth <n>] [--format text|jsonl] [--output <file>] [--exclude-hidden] [--watch] [--files0-from <file>|-] [--strict] [--strict-roots] [--range START:END] [--files-only] [--since <rfc3339>] [--control] [--fail-fast] [--sort name|size|mtime] [--reproducible] [--max-total-bytes <n>] [--quiet] [--verbose] [--verbose-char-table] [--count-char <c>] [--include-empty-dirs] [--dedup] [--top-words <n>] [--path-style <abs|rel|base>] [--metrics <file>] [--baseline <file>] [--max-errors <n>] [--max-runtime <duration>] [--tree] [--lossy] [--record-session <file>] [--replay <file>] <num_threads|auto> [dir1 dir2 ...]",
+            args[0]
+        ));
     }
 
     // Number of worker threads to create
-    let num_threads: usize = args[1].parse().expect("invalid num_threads");
+    let (num_threads, thread_clamp_warning) =
+        parse_num_threads(&args[1]).map_err(|e| format!("Invalid num_threads: {}", e))?;
+    if let Some(warning) = thread_clamp_warning {
+        eprintln!("Warning: {}", warning);
+    }
 
-    // Directories to process
-    let dirs: Vec<String> = args[2..].to_vec();
+    // Directories (and/or individual files) to process
+    let mut dirs: Vec<String> = args[2..].to_vec();
+    if let Some(source) = files0_from {
+        dirs.extend(read_files0(&source)?);
+    }
+    if let Some(path) = &replay_path {
+        // A recorded session lists exact files in exact dispatch order;
+        // replace whatever was passed positionally and disable sorting so
+        // that order survives `collect_file_entries` unchanged.
+        let session = load_session(path)?;
+        dirs = session.entries.into_iter().map(|entry| entry.path).collect();
+        sort = None;
+    }
 
-    // ----------------------------------------
-    // Initialize thread pool and file processor
-    // ----------------------------------------
-    let pool = ThreadPool::new(num_threads);
+    Ok(Cli {
+        config: RunConfig {
+            num_threads,
+            dirs,
+            max_files_per_sec,
+            progress_interval: progress_interval_ms.map(Duration::from_millis),
+            cache_path,
+            match_pattern,
+            max_depth,
+            exclude_hidden,
+            watch,
+            strict,
+            strict_roots,
+            range,
+            files_only,
+            since,
+            control,
+            fail_fast,
+            sort,
+            max_total_bytes,
+            dedup,
+            top_words,
+            path_style,
+            max_errors,
+            lossy,
+            max_runtime,
+        },
+        quiet,
+        verbose,
+        verbose_char_table,
+        count_chars,
+        include_empty_dirs,
+        dedup,
+        top_words,
+        jsonl,
+        output_path,
+        metrics_path,
+        baseline_path,
+        tree,
+        record_session_path,
+    })
+}
+
+/// Parses a `--range START:END` value into a half-open byte range.
+fn parse_range(value: &str) -> Result<(u64, u64), String> {
+    let (start, end) = value
+        .split_once(':')
+        .ok_or_else(|| format!("invalid --range {:?}: expected START:END", value))?;
+    let start: u64 = start
+        .parse()
+        .map_err(|_| format!("invalid --range {:?}: expected START:END", value))?;
+    let end: u64 = end
+        .parse()
+        .map_err(|_| format!("invalid --range {:?}: expected START:END", value))?;
+    if end < start {
+        return Err(format!(
+            "invalid --range {:?}: END must not be before START",
+            value
+        ));
+    }
+    Ok((start, end))
+}
+
+/// Parses a `--sort` value into a `SortOrder`.
+fn parse_sort_order(value: &str) -> Result<SortOrder, String> {
+    match value {
+        "name" => Ok(SortOrder::Name),
+        "size" => Ok(SortOrder::Size),
+        "mtime" => Ok(SortOrder::Mtime),
+        other => Err(format!(
+            "invalid --sort {:?}: expected \"name\", \"size\", or \"mtime\"",
+            other
+        )),
+    }
+}
+
+/// Parses a `--path-style` value into a `PathStyle`.
+fn parse_path_style(value: &str) -> Result<PathStyle, String> {
+    match value {
+        "abs" => Ok(PathStyle::Absolute),
+        "rel" => Ok(PathStyle::Relative),
+        "base" => Ok(PathStyle::Basename),
+        other => Err(format!(
+            "invalid --path-style {:?}: expected \"abs\", \"rel\", or \"base\"",
+            other
+        )),
+    }
+}
+
+/// Parses a `--count-char` value into the single `char` it names. Accepts a
+/// literal character (e.g. `t`) or a backslash escape: `\t`, `\n`, `\r`,
+/// `\0`, `\\`, or `\u{XXXX}` for an arbitrary codepoint such as a
+/// non-breaking space (`\u{00A0}`) or emoji.
+fn parse_count_char(value: &str) -> Result<char, String> {
+    let invalid = || format!("invalid --count-char {:?}: expected a single character", value);
+
+    if let Some(rest) = value.strip_prefix('\\') {
+        return match rest {
+            "t" => Ok('\t'),
+            "n" => Ok('\n'),
+            "r" => Ok('\r'),
+            "0" => Ok('\0'),
+            "\\" => Ok('\\'),
+            _ => {
+                let hex = rest
+                    .strip_prefix("u{")
+                    .and_then(|s| s.strip_suffix('}'))
+                    .ok_or_else(invalid)?;
+                let codepoint = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+                char::from_u32(codepoint).ok_or_else(invalid)
+            }
+        };
+    }
+
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(invalid()),
+    }
+}
+
+/// Runs the processing pipeline to completion and returns its summary.
+/// `on_message` is invoked for every progress event as it arrives, letting
+/// a caller observe live progress (or print it, as `main` does) without
+/// `run` itself writing to stdout.
+fn run(config: RunConfig, mut on_message: impl FnMut(&ProgressMessage)) -> Result<RunSummary, String> {
+    let run_start = Instant::now();
+
+    // `--range` spot-checks a single file directly, bypassing the
+    // directory-traversal/thread-pool pipeline entirely.
+    if let Some((start, end)) = config.range {
+        let path = match config.dirs.as_slice() {
+            [path] => path.clone(),
+            _ => return Err("--range requires exactly one file argument".to_string()),
+        };
+        let analysis = analyzer::analyze_file_range(&path, start, end);
+        let total_errors = analysis.errors.len();
+        if !analysis.errors.is_empty() {
+            on_message(&ProgressMessage::FileWarning(
+                analysis.filename.clone(),
+                analysis.errors.clone(),
+            ));
+        }
+        on_message(&ProgressMessage::FileCompleted(analysis.clone()));
+        return Ok(RunSummary {
+            total_files: 1,
+            total_words: analysis.stats.word_count,
+            total_lines: analysis.stats.line_count,
+            total_bytes: analysis.stats.size_bytes as usize,
+            total_errors,
+            elapsed: run_start.elapsed(),
+            analyses: vec![analysis],
+            peak_queue_depth: 0,
+            average_queue_depth: 0.0,
+            budget_exceeded: false,
+            max_errors_exceeded: false,
+            runtime_exceeded: false,
+            empty_dirs: Vec::new(),
+        });
+    }
+
+    if config.control && config.watch {
+        return Err("--control cannot be combined with --watch".to_string());
+    }
+
+    let pool = ThreadPool::new(config.num_threads);
 
     // FileProcessor manages directory traversal, file analysis,
     // and progress reporting (persisted to progress.json)
-    let processor = FileProcessor::new(pool, "progress.json");
+    let mut processor = FileProcessor::new(pool, "progress.json");
+    processor.set_max_files_per_sec(config.max_files_per_sec);
+    if let Some(interval) = config.progress_interval {
+        processor.set_progress_interval(interval);
+    }
+    processor.set_cache_path(config.cache_path);
+    processor.set_match_pattern(config.match_pattern);
+    processor.set_max_depth(config.max_depth);
+    processor.set_exclude_hidden(config.exclude_hidden);
+    processor.set_files_only(config.files_only);
+    processor.set_since(config.since);
+    processor.set_sort(config.sort);
+    processor.set_path_style(config.path_style);
+    processor.set_max_total_bytes(config.max_total_bytes);
+    processor.set_compute_hash(config.dedup);
+    processor.set_track_words(config.top_words.is_some());
+    processor.set_lossy(config.lossy);
+    let processor = Arc::new(processor);
+
+    // `--control` reads `pause`/`resume`/`cancel`/`status` commands from
+    // stdin on a background thread, applying each one to this batch and
+    // echoing a JSON line with the resulting state, so a GUI or another
+    // process can drive the run interactively.
+    let token = processor.new_cancellation_token();
+    if config.control {
+        let control_processor = Arc::clone(&processor);
+        let control_token = token.clone();
+        thread::spawn(move || {
+            for line in std::io::stdin().lines().map_while(Result::ok) {
+                let Some(command) = control::parse_control_command(&line) else {
+                    continue;
+                };
+                let response =
+                    control::apply_control_command(command, &control_processor, &control_token);
+                if let Ok(json) = serde_json::to_string(&response) {
+                    println!("{}", json);
+                }
+            }
+        });
+    }
 
-    // Begin processing directories and receive a channel for progress updates
-    let rx = processor.process_dirs(dirs);
+    // Held onto separately so `--fail-fast`/`--max-errors` can cancel the
+    // run from inside the message loop below, after `token` itself is
+    // moved into `process_dirs_with_token`.
+    let fail_fast_token = token.clone();
+    let max_errors_token = token.clone();
+    let runtime_token = token.clone();
+
+    // Begin processing directories and receive a channel for progress
+    // updates. `--watch` keeps the channel open past the initial pass,
+    // re-analyzing files as they're created or modified.
+    let rx = if config.watch {
+        processor.process_dirs_watch(config.dirs)
+    } else {
+        processor.process_dirs_with_token(config.dirs, token)
+    };
 
-    // ----------------------------------------
-    // Listen for progress messages
-    // ----------------------------------------
     let mut completed = 0usize;
     let mut total = 0usize;
+    let mut total_errors = 0usize;
+    let mut max_errors_exceeded = false;
+    let mut runtime_exceeded = false;
+    let mut analyses: Vec<FileAnalysis> = Vec::new();
 
     loop {
-        match rx.recv_timeout(Duration::from_millis(500)) {
-            Ok(msg) => match msg {
-                // A file has started processing
-                ProgressMessage::FileStarted(name) => {
-                    println!("Started: {}", name);
-                }
-
-                // A file completed successfully
-                ProgressMessage::FileCompleted(analysis) => {
-                    handle_completed(analysis);
-                    completed += 1;
-                }
-
-                // A file failed during processing
-                ProgressMessage::FileFailed(name, err) => {
-                    eprintln!(
-                        "Failed {}: {} - {}",
-                        name, err.operation, err.message
-                    );
-                    completed += 1;
-                }
+        // `--max-runtime` cancels the dispatcher once the wall-clock budget
+        // is up, the same way `--max-errors` does for an error count: the
+        // run still drains in-flight jobs and prints a partial summary
+        // instead of aborting mid-file. Checked every time we wake up
+        // (message or 500ms timeout) rather than only on timeout, so a
+        // fast-completing batch doesn't overrun the limit waiting on a
+        // timeout that never comes.
+        if !runtime_exceeded && config.max_runtime.is_some_and(|limit| run_start.elapsed() >= limit)
+        {
+            runtime_exceeded = true;
+            runtime_token.cancel();
+        }
 
-                // Periodic update containing total and completed counts
-                ProgressMessage::OverallProgress { completed: c, total: t } => {
-                    total = t;
-                    println!("Progress: {}/{}", c, t);
+        match rx.recv_timeout(Duration::from_millis(500)) {
+            Ok(msg) => {
+                match &msg {
+                    ProgressMessage::FileCompleted(analysis) => {
+                        completed += 1;
+                        analyses.push(analysis.clone());
+                    }
+                    ProgressMessage::FileFailed(name, err) => {
+                        completed += 1;
+                        total_errors += 1;
+                        // A directory argument that doesn't exist is most
+                        // likely a typo; under `--strict`, treat it as
+                        // fatal instead of letting the run "succeed" with
+                        // silently fewer files than intended.
+                        if config.strict && err.operation == "not_found" {
+                            return Err(format!("{}: {} - {}", name, err.operation, err.message));
+                        }
+                        // An unreadable top-level root is, by default, just
+                        // a warning so the other roots still get processed.
+                        // `--strict-roots` instead treats it as fatal; this
+                        // fires before any file is dispatched, since
+                        // directory warnings are sent ahead of the first
+                        // job (see `process_dirs_with_token`).
+                        if config.strict_roots && err.operation == "read_dir_root" {
+                            return Err(format!("{}: {} - {}", name, err.operation, err.message));
+                        }
+                        // `--fail-fast` aborts on the very first failure of
+                        // any kind: cancel so the dispatcher stops
+                        // submitting new files, then return immediately
+                        // rather than waiting for in-flight jobs to drain.
+                        if config.fail_fast {
+                            fail_fast_token.cancel();
+                            return Err(format!(
+                                "{}: {} - {} (fail-fast triggered)",
+                                name, err.operation, err.message
+                            ));
+                        }
+                        // `--max-errors N` instead lets the run continue
+                        // until N failures have accumulated, then cancels
+                        // so no further files are dispatched, but still
+                        // drains in-flight jobs and prints a summary of
+                        // the errors collected so far (unlike fail-fast,
+                        // which aborts immediately on the first failure).
+                        if !max_errors_exceeded
+                            && config.max_errors.is_some_and(|limit| total_errors >= limit)
+                        {
+                            max_errors_exceeded = true;
+                            max_errors_token.cancel();
+                        }
+                    }
+                    ProgressMessage::OverallProgress { total: t, .. } => {
+                        total = *t;
+                    }
+                    ProgressMessage::FileStarted(_) | ProgressMessage::FileWarning(_, _) => {}
                 }
-            },
+                on_message(&msg);
+            }
 
             // Timeout allows the loop to remain responsive
             Err(RecvTimeoutError::Timeout) => {
-                // Timeout reached without receiving a message.
-                // This can be used for heartbeat logs or termination checks.
-                // Example exit condition (not implemented here):
-                // if completed == total && total > 0 { break; }
+                // No message within the timeout window; check whether all
+                // known work has finished so we don't wait forever on a
+                // channel that will never disconnect on its own. In
+                // `--watch` mode the channel is expected to stay open
+                // indefinitely, so this early exit doesn't apply.
+                if !config.watch && total > 0 && completed == total {
+                    break;
+                }
             }
 
             // Channel closed: no more messages will arrive
@@ -101,18 +906,434 @@ fn main() {
         }
     }
 
-    // ----------------------------------------
-    // Final summary output
-    // ----------------------------------------
-    println!(
-        "Done. Total bytes processed: {}",
-        processor.total_bytes_processed()
+    let total_words = analyses.iter().map(|a| a.stats.word_count).sum();
+    let total_lines = analyses.iter().map(|a| a.stats.line_count).sum();
+    let total_bytes = processor.total_bytes_processed();
+    let budget_exceeded = config
+        .max_total_bytes
+        .is_some_and(|budget| total_bytes as u64 >= budget);
+
+    Ok(RunSummary {
+        total_files: completed,
+        total_words,
+        total_lines,
+        total_bytes,
+        total_errors,
+        elapsed: run_start.elapsed(),
+        analyses,
+        peak_queue_depth: processor.pool_peak_queue_depth(),
+        average_queue_depth: processor.pool_average_queue_depth(),
+        budget_exceeded,
+        max_errors_exceeded,
+        runtime_exceeded,
+        empty_dirs: processor.empty_dirs(),
+    })
+}
+
+/// The `--output <file>` report: a JSON snapshot of the run's summary
+/// figures alongside every completed file's `FileAnalysis`, written
+/// independently of whatever stdout sink `--format`/`--quiet`/`--verbose`
+/// select.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Report {
+    total_files: usize,
+    total_bytes: usize,
+    empty_files: usize,
+    elapsed_ms: u64,
+    analyses: Vec<FileAnalysis>,
+}
+
+/// A single file dispatched during a run, recorded by `--record-session`
+/// in the order the dispatcher handed it to the thread pool. `status` is
+/// `"pending"` while the file is still in flight, `"ok"` once it completes
+/// successfully, or `"error"` if it fails; a run that's interrupted before
+/// every dispatched file resolves can leave trailing `"pending"` entries.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct SessionEntry {
+    path: String,
+    status: String,
+}
+
+/// The `--record-session <file>` output: every file dispatched during a
+/// run, in dispatch order, alongside its terminal status. `--replay <file>`
+/// reads this back and substitutes its `path`s for the usual positional
+/// directories, so a later run reprocesses exactly this file list in
+/// exactly this order, pinning down nondeterministic bug reports.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct Session {
+    entries: Vec<SessionEntry>,
+}
+
+/// Reads back a session file written by `--record-session`, for
+/// `--replay <file>` to recover its ordered file list.
+fn load_session(path: &str) -> Result<Session, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read --replay {:?}: {}", path, e))?;
+    serde_json::from_str(&contents).map_err(|e| format!("failed to parse --replay {:?}: {}", path, e))
+}
+
+/// A node in the directory tree built by `build_tree`, holding file count
+/// and total bytes aggregated over every file at or beneath this node.
+#[derive(Debug, Default)]
+struct TreeNode {
+    files: usize,
+    bytes: u64,
+    children: std::collections::BTreeMap<String, TreeNode>,
+}
+
+/// Builds a directory tree from `analyses`, keyed by path component, with
+/// each node's `files`/`bytes` summed over every file in its subtree. Used
+/// to render the `--tree` output, a hierarchical alternative to
+/// `group_by_directory`'s flat per-directory subtotals.
+fn build_tree(analyses: &[FileAnalysis]) -> TreeNode {
+    let mut root = TreeNode::default();
+
+    for analysis in analyses {
+        let components: Vec<String> = std::path::Path::new(&analysis.filename)
+            .parent()
+            .map(|p| {
+                p.components()
+                    .filter_map(|c| c.as_os_str().to_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut node = &mut root;
+        node.files += 1;
+        node.bytes += analysis.stats.size_bytes;
+        for component in components {
+            node = node.children.entry(component).or_default();
+            node.files += 1;
+            node.bytes += analysis.stats.size_bytes;
+        }
+    }
+
+    root
+}
+
+/// Renders `node` (named `name`, at the given indentation `depth`) and its
+/// children as a `tree`-style listing, one line per directory, annotated
+/// with its aggregated `files`/`bytes` totals.
+fn render_tree(node: &TreeNode, name: &str, depth: usize) -> String {
+    let indent = "  ".repeat(depth);
+    let mut out = format!(
+        "{}{} (files={}, bytes={})\n",
+        indent, name, node.files, node.bytes
     );
+    for (child_name, child) in &node.children {
+        out.push_str(&render_tree(child, child_name, depth + 1));
+    }
+    out
+}
+
+/// Per-directory subtotals accumulated by `group_by_directory`.
+#[derive(Debug, Default, Clone, Copy)]
+struct DirSummary {
+    files: usize,
+    words: usize,
+    lines: usize,
+    bytes: u64,
+}
+
+/// Groups completed analyses by their file's parent directory, summing
+/// file count, word count, line count, and byte size per group. Uses a
+/// `BTreeMap` so directories print in a stable, sorted order.
+fn group_by_directory(analyses: &[FileAnalysis]) -> std::collections::BTreeMap<String, DirSummary> {
+    let mut groups: std::collections::BTreeMap<String, DirSummary> = std::collections::BTreeMap::new();
+
+    for analysis in analyses {
+        let parent = std::path::Path::new(&analysis.filename)
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let summary = groups.entry(parent).or_default();
+        summary.files += 1;
+        summary.words += analysis.stats.word_count;
+        summary.lines += analysis.stats.line_count;
+        summary.bytes += analysis.stats.size_bytes;
+    }
+
+    groups
+}
+
+/// Per-extension subtotals accumulated by `group_by_extension`.
+#[derive(Debug, Default, Clone, Copy)]
+struct ExtSummary {
+    files: usize,
+    words: usize,
+    lines: usize,
+    bytes: u64,
+}
+
+/// Groups completed analyses by their file's extension, summing file
+/// count, word count, line count, and byte size per group. Files without
+/// an extension (e.g. `Makefile`) are grouped under `"(none)"`. Returns
+/// the groups sorted by total bytes, largest first, with ties broken by
+/// extension name for a stable order.
+fn group_by_extension(analyses: &[FileAnalysis]) -> Vec<(String, ExtSummary)> {
+    let mut groups: std::collections::HashMap<String, ExtSummary> = std::collections::HashMap::new();
+
+    for analysis in analyses {
+        let ext = std::path::Path::new(&analysis.filename)
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_else(|| "(none)".to_string());
+
+        let summary = groups.entry(ext).or_default();
+        summary.files += 1;
+        summary.words += analysis.stats.word_count;
+        summary.lines += analysis.stats.line_count;
+        summary.bytes += analysis.stats.size_bytes;
+    }
+
+    let mut groups: Vec<(String, ExtSummary)> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes).then_with(|| a.0.cmp(&b.0)));
+    groups
+}
+
+/// Groups completed analyses by `FileStats::language`, counting files per
+/// language. Files with no guessed language are grouped under
+/// `"(unknown)"`. Returns the groups sorted by file count, largest first,
+/// with ties broken by language name for a stable order.
+fn group_by_language(analyses: &[FileAnalysis]) -> Vec<(String, usize)> {
+    let mut groups: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+
+    for analysis in analyses {
+        let language = analysis
+            .stats
+            .language
+            .clone()
+            .unwrap_or_else(|| "(unknown)".to_string());
+        *groups.entry(language).or_insert(0) += 1;
+    }
+
+    let mut groups: Vec<(String, usize)> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    groups
+}
+
+/// Computes aggregate throughput as `(bytes_per_sec, files_per_sec)` given
+/// the total bytes and files processed over `elapsed` wall-clock time.
+/// Returns `(0.0, 0.0)` for a zero (or sub-nanosecond) duration rather
+/// than dividing by zero.
+fn compute_throughput(total_bytes: usize, total_files: usize, elapsed: Duration) -> (f64, f64) {
+    let secs = elapsed.as_secs_f64();
+    if secs <= 0.0 {
+        return (0.0, 0.0);
+    }
+    (total_bytes as f64 / secs, total_files as f64 / secs)
+}
+
+/// Logarithmic bucket boundaries (in milliseconds) for
+/// `processing_time_histogram`, each paired with its display label.
+const PROCESSING_TIME_BUCKETS: &[(u128, &str)] = &[
+    (1, "<1ms"),
+    (10, "1-10ms"),
+    (100, "10-100ms"),
+    (1000, "100ms-1s"),
+    (u128::MAX, ">=1s"),
+];
+
+/// Buckets `analyses` by `processing_time` into logarithmic ranges
+/// (`<1ms`, `1-10ms`, `10-100ms`, `100ms-1s`, `>=1s`), returning counts in
+/// bucket order. Useful for spotting whether most files are fast with a
+/// handful of slow outliers, which a single average would hide.
+fn processing_time_histogram(analyses: &[FileAnalysis]) -> Vec<(&'static str, usize)> {
+    let mut counts = vec![0usize; PROCESSING_TIME_BUCKETS.len()];
+
+    for analysis in analyses {
+        let ms = analysis.processing_time.as_millis();
+        let bucket = PROCESSING_TIME_BUCKETS
+            .iter()
+            .position(|(ceiling, _)| ms < *ceiling)
+            .unwrap_or(PROCESSING_TIME_BUCKETS.len() - 1);
+        counts[bucket] += 1;
+    }
+
+    PROCESSING_TIME_BUCKETS
+        .iter()
+        .map(|(_, label)| *label)
+        .zip(counts)
+        .collect()
+}
+
+/// Tracks the `n` largest-by-key entries seen so far using a bounded
+/// min-heap, so accumulating a "top N" summary (e.g. slowest or largest
+/// files) over many events costs `O(log n)` per event instead of sorting
+/// the entire set at the end.
+struct TopN<K> {
+    n: usize,
+    seq: usize,
+    heap: std::collections::BinaryHeap<std::cmp::Reverse<(K, usize, String)>>,
+}
+
+impl<K: Ord + Copy> TopN<K> {
+    fn new(n: usize) -> Self {
+        TopN {
+            n,
+            seq: 0,
+            heap: std::collections::BinaryHeap::new(),
+        }
+    }
+
+    /// Records an entry, evicting the current smallest-key entry once the
+    /// heap exceeds `n` items. `seq` breaks ties deterministically so
+    /// equal keys don't depend on hash/heap ordering.
+    fn record(&mut self, key: K, name: String) {
+        self.heap.push(std::cmp::Reverse((key, self.seq, name)));
+        self.seq += 1;
+        if self.heap.len() > self.n {
+            self.heap.pop();
+        }
+    }
+
+    /// Consumes the tracker, returning its entries largest-key-first.
+    fn into_sorted(self) -> Vec<(K, String)> {
+        let mut entries: Vec<(K, usize, String)> =
+            self.heap.into_iter().map(|std::cmp::Reverse(t)| t).collect();
+        entries.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        entries.into_iter().map(|(k, _, name)| (k, name)).collect()
+    }
+}
+
+/// Upper clamp on the `num_threads` argument: a user passing an unreasonably large
+/// count (e.g. a typo adding an extra zero) would otherwise spawn that
+/// many OS threads and likely exhaust process or system resources before
+/// any actual work starts.
+const MAX_NUM_THREADS: usize = 1024;
+
+/// Resolves the `num_threads` argument: `"auto"` uses
+/// `std::thread::available_parallelism()`, falling back to 4 if it can't
+/// be determined, while a numeric value must be at least 1. Either form
+/// is then clamped to `MAX_NUM_THREADS`; the second element of the
+/// returned tuple carries a warning message when clamping changed the
+/// requested count, or `None` otherwise.
+fn parse_num_threads(arg: &str) -> Result<(usize, Option<String>), String> {
+    let requested = if arg == "auto" {
+        std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4)
+    } else {
+        let threads: usize = arg
+            .parse()
+            .map_err(|_| format!("{:?} is not \"auto\" or a positive integer", arg))?;
+        if threads == 0 {
+            return Err("thread count must be at least 1".to_string());
+        }
+        threads
+    };
+
+    if requested > MAX_NUM_THREADS {
+        let warning = format!(
+            "requested thread count {} exceeds the maximum of {}; clamping to {}",
+            requested, MAX_NUM_THREADS, MAX_NUM_THREADS
+        );
+        Ok((MAX_NUM_THREADS, Some(warning)))
+    } else {
+        Ok((requested, None))
+    }
+}
+
+/// Reads NUL-separated paths from `source` (`-` for stdin, otherwise a file
+/// path) for `--files0-from`. NUL separators let paths containing newlines
+/// round-trip correctly, matching tools like `find -print0`.
+fn read_files0(source: &str) -> Result<Vec<String>, String> {
+    use std::io::Read;
+
+    let bytes = if source == "-" {
+        let mut buf = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut buf)
+            .map_err(|e| format!("failed to read --files0-from stdin: {}", e))?;
+        buf
+    } else {
+        std::fs::read(source)
+            .map_err(|e| format!("failed to read --files0-from {:?}: {}", source, e))?
+    };
+
+    Ok(parse_files0(&bytes))
+}
+
+/// Splits NUL-separated bytes into paths, dropping the empty trailing chunk
+/// left by a final terminator (if any).
+fn parse_files0(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .split(|&b| b == 0)
+        .filter(|chunk| !chunk.is_empty())
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect()
+}
+
+/// Removes `--flag <value>` from `args` (if present) and returns `value`.
+fn take_flag_value(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let idx = args.iter().position(|a| a == flag)?;
+    args.remove(idx);
+    if idx < args.len() {
+        Some(args.remove(idx))
+    } else {
+        None
+    }
+}
+
+/// Removes `--flag` from `args` (if present), returning whether it was set.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    match args.iter().position(|a| a == flag) {
+        Some(idx) => {
+            args.remove(idx);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Like `take_flag_value`, but removes every `--flag <value>` occurrence
+/// in `args` and returns all of their values, in the order given, for
+/// flags meant to be repeatable (e.g. `--count-char a --count-char b`).
+fn take_flag_values(args: &mut Vec<String>, flag: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    while let Some(value) = take_flag_value(args, flag) {
+        values.push(value);
+    }
+    values
+}
+
+/// A single `--format jsonl` output record: one JSON object per line,
+/// tagged by `status`, so downstream tools can consume results
+/// incrementally instead of waiting for the final summary.
+#[derive(Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum JsonlRecord<'a> {
+    Completed(&'a FileAnalysis),
+    Failed {
+        filename: &'a str,
+        operation: &'a str,
+        message: &'a str,
+    },
+}
+
+/// Prints a completed analysis as a single JSON Lines record to stdout.
+fn print_jsonl_completed(analysis: &FileAnalysis) {
+    if let Ok(line) = serde_json::to_string(&JsonlRecord::Completed(analysis)) {
+        println!("{}", line);
+    }
+}
+
+/// Prints a failed file's error as a single JSON Lines record to stdout.
+fn print_jsonl_failed(filename: &str, err: &ProcessingError) {
+    let record = JsonlRecord::Failed {
+        filename,
+        operation: &err.operation,
+        message: &err.message,
+    };
+    if let Ok(line) = serde_json::to_string(&record) {
+        println!("{}", line);
+    }
 }
 
 /// Handles successful file completion events by printing
 /// detailed statistics about the analyzed file.
-fn handle_completed(analysis: FileAnalysis) {
+fn handle_completed(analysis: &FileAnalysis) {
     println!(
         "Completed: {} (words: {}, lines: {}, bytes: {}, time: {:?}, errs: {})",
         analysis.filename,
@@ -122,4 +1343,210 @@ fn handle_completed(analysis: FileAnalysis) {
         analysis.processing_time,
         analysis.errors.len()
     );
+    if let Some(count) = analysis.stats.regex_match_count {
+        println!("  Matches: {}", count);
+    }
+}
+
+/// Like `handle_completed`, but additionally prints the top character
+/// frequencies, the detected line-ending/indentation style, and (when a
+/// `--match` regex is active) every matching line with its line number,
+/// grep-like, for use as a diagnostic when `--verbose` is passed. When
+/// `char_table` is set, also prints the full character frequency map
+/// sorted by codepoint via `--verbose-char-table`.
+fn handle_completed_verbose(analysis: &FileAnalysis, char_table: bool) {
+    handle_completed(analysis);
+    println!("  Top chars: {:?}", top_chars(&analysis.stats.char_frequencies, 5));
+    println!("  Line ending: {:?}", analysis.stats.line_ending);
+    println!("  Indentation: {:?}", analysis.stats.indentation);
+    if analysis.stats.lines_with_trailing_ws > 0 {
+        println!(
+            "  Lines with trailing whitespace: {}",
+            analysis.stats.lines_with_trailing_ws
+        );
+    }
+    if let Some(matches) = &analysis.stats.regex_matches {
+        for (line_no, text) in matches {
+            println!("  {}:{}: {}", analysis.filename, line_no, text);
+        }
+    }
+    if char_table {
+        println!("  Char table (by codepoint):");
+        for (c, count) in chars_by_codepoint(&analysis.stats.char_frequencies) {
+            println!("    {}: {}", escape_char(c), count);
+        }
+    }
+}
+
+/// Returns the `n` most frequent characters in `freq`, most frequent
+/// first, breaking ties by character value for deterministic output.
+fn top_chars(freq: &std::collections::HashMap<char, usize>, n: usize) -> Vec<(char, usize)> {
+    let mut entries: Vec<(char, usize)> = freq.iter().map(|(&c, &count)| (c, count)).collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    entries.truncate(n);
+    entries
+}
+
+/// Returns every entry of `freq` sorted ascending by Unicode codepoint.
+/// `HashMap` iteration order is nondeterministic, so callers that need a
+/// reproducible diff (e.g. `--verbose-char-table`) must collect and sort
+/// into a `Vec` rather than iterating the map directly.
+fn chars_by_codepoint(freq: &std::collections::HashMap<char, usize>) -> Vec<(char, usize)> {
+    let mut entries: Vec<(char, usize)> = freq.iter().map(|(&c, &count)| (c, count)).collect();
+    entries.sort_by_key(|&(c, _)| c);
+    entries
+}
+
+/// Renders a character for diagnostic output, escaping it the way Rust's
+/// `Debug` for `char` does (e.g. `'\n'`, `'\t'`, `'\u{7}'`) so non-printable
+/// characters don't corrupt the terminal or make the output ambiguous.
+fn escape_char(c: char) -> String {
+    format!("{:?}", c)
+}
+
+/// Prints, for a single completed file, the count of each character in
+/// `count_chars` (as requested via `--count-char`), pulled from the
+/// already-computed `char_frequencies` rather than rescanning the file.
+fn print_char_counts(analysis: &FileAnalysis, count_chars: &[char]) {
+    for &c in count_chars {
+        let count = analysis.stats.char_frequencies.get(&c).copied().unwrap_or(0);
+        println!("  Count {}: {}", escape_char(c), count);
+    }
+}
+
+/// Sums the count of each character in `count_chars` across every analysis
+/// in `analyses`, in the order `count_chars` was given, for the final
+/// summary's aggregate report.
+fn aggregate_char_counts(analyses: &[FileAnalysis], count_chars: &[char]) -> Vec<(char, usize)> {
+    count_chars
+        .iter()
+        .map(|&c| {
+            let total = analyses
+                .iter()
+                .map(|a| a.stats.char_frequencies.get(&c).copied().unwrap_or(0))
+                .sum();
+            (c, total)
+        })
+        .collect()
+}
+
+/// Merges each analysis's `word_frequencies` (populated when `--top-words`
+/// enables `FileProcessor::set_track_words`) into a single corpus-wide word
+/// count, then returns the `n` most common words with their totals, most
+/// common first. Ties are broken lexicographically, matching
+/// `FileStats::most_common_word`.
+fn aggregate_top_words(analyses: &[FileAnalysis], n: usize) -> Vec<(String, usize)> {
+    let mut totals: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for analysis in analyses {
+        for (word, count) in &analysis.stats.word_frequencies {
+            *totals.entry(word.clone()).or_insert(0) += count;
+        }
+    }
+
+    let mut ranked: Vec<(String, usize)> = totals.into_iter().collect();
+    ranked.sort_by(|(word_a, count_a), (word_b, count_b)| {
+        count_b.cmp(count_a).then_with(|| word_a.cmp(word_b))
+    });
+    ranked.truncate(n);
+    ranked
+}
+
+/// Groups `analyses` by `content_hash` (populated when `--dedup` enables
+/// `FileProcessor::set_compute_hash`), keeping only groups with more than
+/// one file, for the final summary's duplicate-content report. Files
+/// without a hash (e.g. when hashing failed) are excluded rather than
+/// grouped together under a missing key. Groups are sorted by hash for a
+/// stable, reproducible report order.
+fn find_duplicate_groups(analyses: &[FileAnalysis]) -> Vec<(String, Vec<String>)> {
+    let mut by_hash: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
+    for analysis in analyses {
+        if let Some(hash) = &analysis.stats.content_hash {
+            by_hash
+                .entry(hash.clone())
+                .or_default()
+                .push(analysis.filename.clone());
+        }
+    }
+    let mut groups: Vec<(String, Vec<String>)> = by_hash
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    groups
+}
+
+/// Per-file word/line/byte change between a `--baseline` report and the
+/// current run, for a file present in both.
+#[derive(Debug, Clone)]
+struct FileDelta {
+    filename: String,
+    word_delta: i64,
+    line_delta: i64,
+    byte_delta: i64,
+}
+
+/// The full set of changes between a `--baseline` report and the current
+/// run: files only the baseline had, files only the current run has, and
+/// per-file deltas (plus their sum) for files present in both. Tracking
+/// growth over time this way only requires keeping old `--output` reports
+/// around, not re-running anything.
+#[derive(Debug, Clone, Default)]
+struct BaselineDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<FileDelta>,
+    total_word_delta: i64,
+    total_line_delta: i64,
+    total_byte_delta: i64,
+}
+
+/// Computes `BaselineDiff` by matching `baseline` and `current` analyses
+/// on `filename`. Files present in only one side are reported as
+/// `added`/`removed` rather than a delta against zero, so a file that
+/// simply wasn't scanned this time doesn't look like it shrank to nothing.
+fn compute_baseline_diff(baseline: &[FileAnalysis], current: &[FileAnalysis]) -> BaselineDiff {
+    let baseline_by_name: std::collections::HashMap<&str, &FileAnalysis> = baseline
+        .iter()
+        .map(|a| (a.filename.as_str(), a))
+        .collect();
+    let current_by_name: std::collections::HashMap<&str, &FileAnalysis> = current
+        .iter()
+        .map(|a| (a.filename.as_str(), a))
+        .collect();
+
+    let mut diff = BaselineDiff::default();
+
+    for analysis in current {
+        match baseline_by_name.get(analysis.filename.as_str()) {
+            None => diff.added.push(analysis.filename.clone()),
+            Some(before) => {
+                let word_delta =
+                    analysis.stats.word_count as i64 - before.stats.word_count as i64;
+                let line_delta =
+                    analysis.stats.line_count as i64 - before.stats.line_count as i64;
+                let byte_delta =
+                    analysis.stats.size_bytes as i64 - before.stats.size_bytes as i64;
+                diff.total_word_delta += word_delta;
+                diff.total_line_delta += line_delta;
+                diff.total_byte_delta += byte_delta;
+                diff.changed.push(FileDelta {
+                    filename: analysis.filename.clone(),
+                    word_delta,
+                    line_delta,
+                    byte_delta,
+                });
+            }
+        }
+    }
+
+    for analysis in baseline {
+        if !current_by_name.contains_key(analysis.filename.as_str()) {
+            diff.removed.push(analysis.filename.clone());
+        }
+    }
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.changed.sort_by(|a, b| a.filename.cmp(&b.filename));
+    diff
 }
@@ -4,17 +4,111 @@
 // thread pool, progress reporting, cancellation handling, and persistence
 // of overall progress to disk.
 
-use crate::analyzer::{analyze_file, FileAnalysis, ProcessingError};
+use crate::analyzer::{
+    analyze_file_chunked, analyze_file_with_options, analyze_reader, hash_file,
+    scan_regex_matches, word_frequencies, AnalyzeOptions, FileAnalysis, ProcessingError,
+};
+use crate::cache::{self, Cache};
 use crate::thread_pool::ThreadPool;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::Path;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
 use std::thread;
 use std::time;
 
+/// Minimum number of files between `OverallProgress` updates when
+/// dispatch isn't rate-limited, so large runs don't flood the channel.
+const PROGRESS_COALESCE_FILES: usize = 50;
+
+/// Default minimum wall-clock time between `OverallProgress` updates.
+/// Overridable via `FileProcessor::set_progress_interval`.
+const DEFAULT_PROGRESS_INTERVAL: time::Duration = time::Duration::from_millis(100);
+
+/// Default number of completed files between progress-file checkpoints.
+/// Overridable via `FileProcessor::set_checkpoint_every`.
+const DEFAULT_CHECKPOINT_EVERY: usize = 50;
+
+/// Default quiet period a watched path must go without further events
+/// before `process_dirs_watch` re-analyzes it, so a burst of writes to
+/// the same file doesn't trigger one re-analysis per write. Overridable
+/// via `FileProcessor::set_watch_debounce`.
+const DEFAULT_WATCH_DEBOUNCE: time::Duration = time::Duration::from_millis(200);
+
+/// Capacity of the bounded `ProgressMessage` channel used by
+/// `process_dirs_with_token` and `process_dirs_watch`. A `FileCompleted`
+/// message carries a whole `FileAnalysis`, including its character
+/// frequency map, so an unbounded channel would let a slow consumer's
+/// backlog grow without limit. With a bounded `sync_channel`, dispatcher
+/// and worker threads instead block on `send` once the channel fills,
+/// applying backpressure all the way back to job submission.
+///
+/// Callers of `process_dirs`/`process_dirs_with_token`/`process_dirs_watch`
+/// MUST keep draining the returned `Receiver` (e.g. in a loop, as
+/// `process_dirs_collect` does) for as long as the batch is running;
+/// a consumer that stops reading partway through will stall every
+/// worker thread once this many messages are buffered.
+pub const PROGRESS_CHANNEL_CAPACITY: usize = 64;
+
+/// Schema version written to persisted progress files by this build.
+/// Bump this whenever `ProgressState`'s fields change in a way that
+/// would make an older reader misinterpret a newer file, or vice versa.
+const PROGRESS_STATE_VERSION: u32 = 1;
+
+/// The on-disk shape of a persisted progress file, written by
+/// `persist_progress` and read back by `load_progress`. `version` lets
+/// `load_progress` reject files written by an incompatible schema
+/// instead of silently misparsing or reporting wrong counts.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProgressState {
+    version: u32,
+    completed: usize,
+    total: usize,
+}
+
+/// A cancellation flag scoped to a single `process_dirs_with_token` batch,
+/// so a caller running several batches concurrently on the same
+/// `FileProcessor` can cancel one without affecting the others. Cloning a
+/// token shares its scope: cancelling any clone cancels all of them.
+/// `FileProcessor::cancel` remains a global convenience that cancels every
+/// token, since each one also observes the processor's shared flag.
+#[derive(Clone)]
+pub struct CancellationToken {
+    local: Arc<AtomicBool>,
+    global: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    fn new(global: Arc<AtomicBool>) -> Self {
+        CancellationToken {
+            local: Arc::new(AtomicBool::new(false)),
+            global,
+        }
+    }
+
+    /// Cancels this token's batch only. Other batches on the same
+    /// `FileProcessor`, and any created after this call, are unaffected.
+    #[allow(dead_code)]
+    pub fn cancel(&self) {
+        self.local.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` if either this token or the processor's global
+    /// `cancel()` has been triggered.
+    pub fn is_cancelled(&self) -> bool {
+        self.local.load(Ordering::SeqCst) || self.global.load(Ordering::SeqCst)
+    }
+}
+
 /// Messages sent from worker threads to the main thread
 /// to report progress and status updates.
+// `FileCompleted` carries a full `FileAnalysis` by value rather than
+// boxing it: messages are sent one per file rather than batched, so the
+// larger stack copy is outweighed by keeping the common case (matching
+// on and moving the analysis straight into a `Vec`) allocation-free.
+#[allow(clippy::large_enum_variant)]
 pub enum ProgressMessage {
     /// A file has begun processing
     FileStarted(String),
@@ -25,10 +119,66 @@ pub enum ProgressMessage {
     /// A file failed to process
     FileFailed(String, ProcessingError),
 
+    /// A file completed successfully but recorded non-fatal errors along
+    /// the way (e.g. a `read_line` failure that triggered the binary
+    /// fallback). Sent immediately before the corresponding
+    /// `FileCompleted`.
+    FileWarning(String, Vec<ProcessingError>),
+
     /// Overall progress update
     OverallProgress { completed: usize, total: usize },
 }
 
+/// Totals produced by [`FileProcessor::process_dirs_aggregated`]. Each
+/// worker accumulates these directly as files finish and merges into the
+/// shared total once, at shutdown, rather than the caller reducing them
+/// from a stream of [`ProgressMessage`]s.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AggregateStats {
+    pub files: usize,
+    pub bytes: usize,
+    pub words: usize,
+    pub lines: usize,
+    pub errors: usize,
+}
+
+impl AggregateStats {
+    fn merge(&mut self, other: &AggregateStats) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+        self.words += other.words;
+        self.lines += other.lines;
+        self.errors += other.errors;
+    }
+}
+
+/// A point-in-time snapshot of a batch's progress, returned by
+/// `FileProcessor::status` for the `control` line-protocol interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ProcessorStatus {
+    pub completed: usize,
+    pub total: usize,
+    pub bytes: usize,
+}
+
+/// Order in which `FileProcessor::set_sort` arranges collected paths
+/// before dispatch, for reproducible runs across platforms whose
+/// `read_dir` order would otherwise vary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// Lexicographic order by full path.
+    Name,
+    /// Ascending order by file size in bytes. A file whose size can't be
+    /// read sorts as if it were zero bytes.
+    Size,
+    /// Ascending order by modification time (oldest first). A file whose
+    /// mtime can't be read sorts as if it were the Unix epoch.
+    Mtime,
+}
+
+/// A per-file post-processing hook registered via `FileProcessor::set_on_complete`.
+type OnCompleteHook = Arc<dyn Fn(&FileAnalysis) + Send + Sync>;
+
 /// Manages file processing using a thread pool and reports progress.
 ///
 /// Responsibilities:
@@ -49,6 +199,151 @@ pub struct FileProcessor {
 
     /// Path where progress information is persisted (JSON)
     persist_path: String,
+
+    /// Number of completed files between progress-file checkpoints, to
+    /// avoid write amplification on large runs. A final checkpoint is
+    /// always written once processing finishes, regardless of this value.
+    checkpoint_every: usize,
+
+    /// Maximum number of files the dispatcher may submit per second.
+    /// `None` (the default) applies no pacing.
+    max_files_per_sec: Option<f64>,
+
+    /// Minimum wall-clock time between coalesced `OverallProgress` updates.
+    progress_interval: time::Duration,
+
+    /// Path to an on-disk cache mapping a file's path + mtime + size to its
+    /// previously computed `FileAnalysis`. `None` (the default) disables
+    /// caching entirely.
+    cache_path: Option<String>,
+
+    /// A regex to count (and collect) matching lines for, populating
+    /// `FileStats::regex_match_count`/`regex_matches`. `None` (the
+    /// default) skips the extra scan entirely.
+    match_pattern: Option<Regex>,
+
+    /// Maximum number of levels to descend below each directory passed to
+    /// `process_dirs` (`Some(0)` collects only a directory's immediate
+    /// files). `None` (the default) descends without limit.
+    max_depth: Option<usize>,
+
+    /// When set, only files whose extension (without the leading dot)
+    /// appears in this list are collected. `None` (the default) collects
+    /// files regardless of extension.
+    extensions: Option<Vec<String>>,
+
+    /// Compiled glob patterns (e.g. `*.log`); a file whose path matches
+    /// any of them is skipped during collection.
+    exclude_globs: Vec<Regex>,
+
+    /// When `true`, entries whose file name starts with `.` are skipped
+    /// during traversal, and hidden directories aren't descended into.
+    /// Unlike `exclude_globs`, this applies uniformly regardless of path
+    /// and needs no pattern to configure. `false` by default.
+    exclude_hidden: bool,
+
+    /// Files larger than this many bytes are skipped during collection.
+    /// `None` (the default) applies no size limit.
+    max_file_size: Option<u64>,
+
+    /// Files smaller than this many bytes are skipped during collection,
+    /// complementing `max_file_size`. Useful for ignoring placeholder or
+    /// empty files in bulk. `None` (the default) applies no minimum.
+    min_file_size: Option<u64>,
+
+    /// Only files modified strictly after this time are collected, for
+    /// incremental scans. A file whose mtime can't be read is collected
+    /// anyway, with a warning, rather than silently dropped. `None` (the
+    /// default) applies no filter.
+    since: Option<time::SystemTime>,
+
+    /// Maximum wall-clock time allowed for analyzing a single file before
+    /// it's reported as failed with operation `"timeout"`. `None` (the
+    /// default) applies no limit.
+    timeout: Option<time::Duration>,
+
+    /// Number of additional attempts made to analyze a file after it
+    /// comes back with non-fatal errors or times out, before giving up.
+    /// `0` (the default) makes no retries.
+    retries: usize,
+
+    /// Quiet period a path must go without further create/modify events
+    /// before `process_dirs_watch` re-analyzes it. Overridable via
+    /// `FileProcessor::set_watch_debounce`.
+    watch_debounce: time::Duration,
+
+    /// When `true`, every entry in `dirs` must be a regular file: directory
+    /// expansion is skipped entirely, and a directory (or anything else
+    /// that isn't a regular file) is reported as an error instead of being
+    /// silently descended into. `false` (the default) expands directories
+    /// as usual.
+    files_only: bool,
+
+    /// Gates the dispatcher for the line-protocol control interface (see
+    /// `control`): while the bool is `true`, the dispatcher blocks on the
+    /// `Condvar` between files instead of submitting further jobs. `false`
+    /// (the default) never blocks.
+    paused: Arc<(Mutex<bool>, Condvar)>,
+
+    /// Number of files dispatched so far in the current (or most recent)
+    /// batch, for the control interface's `status` command. Mirrors the
+    /// `completed` count reported via `ProgressMessage::OverallProgress`.
+    dispatched_count: Arc<AtomicUsize>,
+
+    /// Total number of files in the current (or most recent) batch, for
+    /// the control interface's `status` command. `0` before a batch has
+    /// been collected.
+    total_count: Arc<AtomicUsize>,
+
+    /// Order to sort collected paths into before dispatch. `None` (the
+    /// default) dispatches in `read_dir` order, which varies by platform.
+    sort: Option<SortOrder>,
+    path_style: Option<PathStyle>,
+
+    /// Cumulative byte budget for a batch: once `total_bytes_processed`
+    /// reaches this many bytes, the dispatcher stops submitting further
+    /// files and the run ends cleanly instead of continuing to exhaustion.
+    /// `None` (the default) applies no budget.
+    max_total_bytes: Option<u64>,
+
+    /// Directories visited during the most recent collection that
+    /// contained no matching files anywhere in their subtree, for
+    /// `empty_dirs` to report to callers auditing a tree for cleanup.
+    /// Repopulated (replacing any previous contents) each time
+    /// `process_dirs`/`process_dirs_with_token` collects files.
+    empty_dirs: Arc<Mutex<Vec<String>>>,
+
+    /// Optional hook invoked with each file's `FileAnalysis` right after it
+    /// finishes analysis, for library users who want to do their own
+    /// per-file work (e.g. write to a database) without modifying the core
+    /// pipeline. Called once per completed file, including cache hits and
+    /// tar entries. Runs on whichever thread produced the analysis — a
+    /// worker thread for freshly analyzed files, the dispatcher thread for
+    /// cache hits — so it must be thread-safe. `None` (the default) does
+    /// nothing extra.
+    on_complete: Option<OnCompleteHook>,
+
+    /// When `true`, computes a SHA-256 digest of each file's raw bytes,
+    /// populating `FileStats::content_hash`, so callers (e.g. the CLI's
+    /// `--dedup` mode) can group files with identical content. `false`
+    /// (the default) skips the extra read hashing requires.
+    compute_hash: bool,
+
+    /// When `true`, builds a per-word occurrence map for each file,
+    /// populating `FileStats::word_frequencies`/`most_common_word`, so
+    /// callers (e.g. the CLI's `--top-words` mode) can aggregate word
+    /// counts across a whole corpus. `false` (the default) skips the
+    /// extra read this requires.
+    track_words: bool,
+
+    /// When `true`, a file that fails UTF-8 decoding is decoded lossily
+    /// (substituting U+FFFD for invalid sequences) and run through the
+    /// normal text analysis instead of the raw-byte fallback, trading the
+    /// chunked-parallel fast path on large files (which this bypasses) for
+    /// `FileStats::replacement_chars` and real word/line/char stats on
+    /// files that aren't quite valid UTF-8. `false` (the default) keeps
+    /// the usual `analyze_file_chunked` behavior.
+    lossy: bool,
 }
 
 impl FileProcessor {
@@ -59,102 +354,663 @@ impl FileProcessor {
             cancellation: Arc::new(AtomicBool::new(false)),
             total_bytes_processed: Arc::new(AtomicUsize::new(0)),
             persist_path: persist_path.into(),
+            checkpoint_every: DEFAULT_CHECKPOINT_EVERY,
+            max_files_per_sec: None,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            cache_path: None,
+            match_pattern: None,
+            max_depth: None,
+            extensions: None,
+            exclude_globs: Vec::new(),
+            exclude_hidden: false,
+            max_file_size: None,
+            min_file_size: None,
+            since: None,
+            timeout: None,
+            retries: 0,
+            watch_debounce: DEFAULT_WATCH_DEBOUNCE,
+            files_only: false,
+            paused: Arc::new((Mutex::new(false), Condvar::new())),
+            dispatched_count: Arc::new(AtomicUsize::new(0)),
+            total_count: Arc::new(AtomicUsize::new(0)),
+            sort: None,
+            path_style: None,
+            max_total_bytes: None,
+            empty_dirs: Arc::new(Mutex::new(Vec::new())),
+            on_complete: None,
+            compute_hash: false,
+            track_words: false,
+            lossy: false,
         }
     }
 
     /// Signals all processing threads to cancel execution
+    #[allow(dead_code)]
     pub fn cancel(&self) {
         self.cancellation.store(true, Ordering::SeqCst);
     }
 
-    /// Processes a list of directories and returns a receiver
-    /// for progress updates.
+    /// Creates a new `CancellationToken` scoped to this processor: it
+    /// observes the processor's global `cancel()`, but can also be
+    /// cancelled on its own without affecting other tokens or batches.
+    #[allow(dead_code)]
+    pub fn new_cancellation_token(&self) -> CancellationToken {
+        CancellationToken::new(Arc::clone(&self.cancellation))
+    }
+
+    /// Clears the global cancellation flag set by `cancel()` and zeroes
+    /// `total_bytes_processed`, so this processor can be reused for a
+    /// second batch after a cancelled (or completed) run instead of
+    /// needing a freshly constructed one.
+    ///
+    /// Must not be called while a run is still active: a batch started
+    /// with `process_dirs`/`process_dirs_with_token` reads and writes
+    /// these same fields concurrently, so resetting them mid-run would
+    /// race with that batch rather than cleanly starting a new one.
+    #[allow(dead_code)]
+    pub fn reset(&self) {
+        self.cancellation.store(false, Ordering::SeqCst);
+        self.total_bytes_processed.store(0, Ordering::SeqCst);
+    }
+
+    /// Pauses the dispatcher: it finishes submitting any file already in
+    /// flight, then blocks before submitting the next one until `resume`
+    /// is called. Used by the `control` line-protocol interface's `pause`
+    /// command.
+    pub fn pause(&self) {
+        *self.paused.0.lock().unwrap() = true;
+    }
+
+    /// Resumes a dispatcher paused by `pause`, waking it immediately.
+    pub fn resume(&self) {
+        *self.paused.0.lock().unwrap() = false;
+        self.paused.1.notify_all();
+    }
+
+    /// Returns `true` if the dispatcher is currently paused.
+    pub fn is_paused(&self) -> bool {
+        *self.paused.0.lock().unwrap()
+    }
+
+    /// Reports the current (or most recently finished) batch's progress
+    /// and total bytes processed, for the `control` interface's `status`
+    /// command.
+    pub fn status(&self) -> ProcessorStatus {
+        ProcessorStatus {
+            completed: self.dispatched_count.load(Ordering::SeqCst),
+            total: self.total_count.load(Ordering::SeqCst),
+            bytes: self.total_bytes_processed.load(Ordering::SeqCst),
+        }
+    }
+
+    /// Sets a cap on how many files the dispatcher may submit per second.
+    /// Pass `None` to dispatch as fast as the thread pool can accept jobs.
+    pub fn set_max_files_per_sec(&mut self, rate: Option<f64>) {
+        self.max_files_per_sec = rate;
+    }
+
+    /// Sets the minimum wall-clock time between coalesced `OverallProgress`
+    /// updates (default 100ms). A final update is always sent regardless
+    /// of this interval.
+    pub fn set_progress_interval(&mut self, interval: time::Duration) {
+        self.progress_interval = interval;
+    }
+
+    /// Sets the number of completed files between progress-file
+    /// checkpoints (default 50). A value of `0` is treated as `1`, since
+    /// writing on every file is the most frequent checkpointing makes
+    /// sense to support.
+    #[allow(dead_code)]
+    pub fn set_checkpoint_every(&mut self, checkpoint_every: usize) {
+        self.checkpoint_every = checkpoint_every;
+    }
+
+    /// Sets the path to an on-disk cache of analysis results, keyed by a
+    /// file's path, modification time, and size. Pass `None` to disable
+    /// caching. When enabled, a subsequent run over an unchanged file skips
+    /// re-analyzing it and emits the cached `FileAnalysis` instead.
+    pub fn set_cache_path(&mut self, path: Option<String>) {
+        self.cache_path = path;
+    }
+
+    /// Sets a regex to scan each file for. When set, matching lines are
+    /// counted (and collected) into `FileStats::regex_match_count`/
+    /// `regex_matches`. Pass `None` to disable the extra scan.
+    pub fn set_match_pattern(&mut self, pattern: Option<Regex>) {
+        self.match_pattern = pattern;
+    }
+
+    /// Sets the maximum number of levels to descend below each directory
+    /// passed to `process_dirs`. `Some(0)` collects only a directory's
+    /// immediate files; `None` (the default) descends without limit.
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Restricts collection to files whose extension (without the leading
+    /// dot) appears in `extensions`. Pass `None` to collect files
+    /// regardless of extension.
+    #[allow(dead_code)]
+    pub fn set_extensions(&mut self, extensions: Option<Vec<String>>) {
+        self.extensions = extensions;
+    }
+
+    /// Sets glob patterns (e.g. `*.log`, matched against the full path)
+    /// for files to exclude from collection. Invalid patterns are
+    /// silently dropped, matching the "surface, don't crash on bad user
+    /// input" handling used for `--match` at the CLI layer.
+    #[allow(dead_code)]
+    pub fn set_exclude_globs(&mut self, patterns: &[String]) {
+        self.exclude_globs = patterns.iter().filter_map(|p| glob_to_regex(p)).collect();
+    }
+
+    /// When `true`, skips files and directories whose name starts with
+    /// `.` during traversal, pruning hidden directories entirely rather
+    /// than just filtering their contents. `false` (the default) collects
+    /// hidden entries like any other.
+    pub fn set_exclude_hidden(&mut self, exclude_hidden: bool) {
+        self.exclude_hidden = exclude_hidden;
+    }
+
+    /// When `true`, requires every entry passed to `process_dirs` to be a
+    /// regular file, skipping directory expansion entirely and reporting a
+    /// directory (or anything else that isn't a regular file) as an error
+    /// rather than descending into it. `false` (the default) expands
+    /// directories as usual.
+    pub fn set_files_only(&mut self, files_only: bool) {
+        self.files_only = files_only;
+    }
+
+    /// Sets the quiet period `process_dirs_watch` waits after a path's
+    /// last create/modify event before re-analyzing it (default 200ms).
+    #[allow(dead_code)]
+    pub fn set_watch_debounce(&mut self, debounce: time::Duration) {
+        self.watch_debounce = debounce;
+    }
+
+    /// Sets the maximum file size, in bytes, that will be collected for
+    /// analysis. Pass `None` to apply no limit.
+    #[allow(dead_code)]
+    pub fn set_max_file_size(&mut self, max_file_size: Option<u64>) {
+        self.max_file_size = max_file_size;
+    }
+
+    /// Sets the minimum file size, in bytes, that will be collected for
+    /// analysis, complementing `set_max_file_size`. Pass `None` to apply no
+    /// minimum.
+    #[allow(dead_code)]
+    pub fn set_min_file_size(&mut self, min_file_size: Option<u64>) {
+        self.min_file_size = min_file_size;
+    }
+
+    /// Sets the cutoff for incremental scans: only files modified strictly
+    /// after `since` are collected. Pass `None` to apply no filter.
+    #[allow(dead_code)]
+    pub fn set_since(&mut self, since: Option<time::SystemTime>) {
+        self.since = since;
+    }
+
+    /// Sets the order collected paths are sorted into before dispatch.
+    /// Pass `None` to dispatch in `read_dir` order.
+    #[allow(dead_code)]
+    pub fn set_sort(&mut self, sort: Option<SortOrder>) {
+        self.sort = sort;
+    }
+
+    /// Sets how collected `filename`s are rendered before dispatch: as
+    /// absolute paths, as paths relative to the scanned root directory,
+    /// or as bare basenames. Pass `None` to dispatch paths exactly as
+    /// `collect_file_entries` discovered them (the default).
+    #[allow(dead_code)]
+    pub fn set_path_style(&mut self, path_style: Option<PathStyle>) {
+        self.path_style = path_style;
+    }
+
+    /// Sets a cumulative byte budget for a batch: once
+    /// `total_bytes_processed` reaches `max_total_bytes`, the dispatcher
+    /// stops submitting further files. Pass `None` to apply no budget.
+    #[allow(dead_code)]
+    pub fn set_max_total_bytes(&mut self, max_total_bytes: Option<u64>) {
+        self.max_total_bytes = max_total_bytes;
+    }
+
+    /// Sets the maximum wall-clock time allowed to analyze a single file
+    /// before it's reported as failed with operation `"timeout"`. Pass
+    /// `None` to apply no limit.
+    #[allow(dead_code)]
+    pub fn set_timeout(&mut self, timeout: Option<time::Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Sets the number of additional attempts made to analyze a file after
+    /// it comes back with non-fatal errors or times out, before giving up.
+    #[allow(dead_code)]
+    pub fn set_retries(&mut self, retries: usize) {
+        self.retries = retries;
+    }
+
+    /// Sets a hook invoked with each file's `FileAnalysis` right after it
+    /// finishes analysis, for library users who want to run their own
+    /// per-file post-processing (e.g. sending results to a database)
+    /// without modifying the core pipeline. The hook runs on whichever
+    /// thread produced the analysis (a worker thread for most files, the
+    /// dispatcher thread for cache hits), so it must be thread-safe. Pass
+    /// `None` to remove a previously set hook.
+    #[allow(dead_code)]
+    pub fn set_on_complete(
+        &mut self,
+        hook: Option<OnCompleteHook>,
+    ) {
+        self.on_complete = hook;
+    }
+
+    /// When `true`, computes a SHA-256 digest of each file's raw bytes
+    /// alongside its normal analysis, populating `FileStats::content_hash`.
+    /// `false` (the default) skips the extra read hashing requires.
+    pub fn set_compute_hash(&mut self, compute_hash: bool) {
+        self.compute_hash = compute_hash;
+    }
+
+    /// When `true`, builds a per-word occurrence map for each file alongside
+    /// its normal analysis, populating `FileStats::word_frequencies` and
+    /// `FileStats::most_common_word`. `false` (the default) skips the extra
+    /// read building the map requires.
+    pub fn set_track_words(&mut self, track_words: bool) {
+        self.track_words = track_words;
+    }
+
+    /// When `true`, a file that fails UTF-8 decoding is decoded lossily and
+    /// analyzed as text (populating `FileStats::replacement_chars`)
+    /// instead of dropping to the raw-byte fallback. `false` (the default)
+    /// keeps the usual binary-fallback behavior.
+    pub fn set_lossy(&mut self, lossy: bool) {
+        self.lossy = lossy;
+    }
+
+    /// Processes a list of directories and returns a receiver for
+    /// progress updates, using a fresh processor-scoped `CancellationToken`
+    /// that isn't handed back, so this batch can only be stopped by the
+    /// processor's global `cancel()`, which stops every other batch too.
+    /// Use `process_dirs_with_token` to scope cancellation to just this
+    /// batch while other concurrent batches on the same processor continue.
+    ///
+    /// The returned channel has bounded capacity (see
+    /// `PROGRESS_CHANNEL_CAPACITY`): the caller must keep draining it, or
+    /// dispatcher and worker threads will block on `send` once it fills.
+    pub fn process_dirs(&self, dirs: Vec<String>) -> mpsc::Receiver<ProgressMessage> {
+        self.process_dirs_with_token(dirs, self.new_cancellation_token())
+    }
+
+    /// Like `process_dirs`, but accepts a `CancellationToken` so a caller
+    /// running several batches concurrently on the same processor can
+    /// cancel one without affecting the others.
     ///
     /// This function:
     /// - Collects all files from the provided directories
     /// - Submits file analysis jobs to the thread pool
     /// - Sends progress updates over an MPSC channel
-    pub fn process_dirs(&self, dirs: Vec<String>) -> mpsc::Receiver<ProgressMessage> {
-        let (tx, rx) = mpsc::channel::<ProgressMessage>();
+    #[allow(dead_code)]
+    pub fn process_dirs_with_token(
+        &self,
+        dirs: Vec<String>,
+        token: CancellationToken,
+    ) -> mpsc::Receiver<ProgressMessage> {
+        let (tx, rx) = mpsc::sync_channel::<ProgressMessage>(PROGRESS_CHANNEL_CAPACITY);
 
-        // Collect all file paths to be processed
-        let paths = collect_files(dirs);
+        // Collect all file paths to be processed, along with a warning for
+        // each directory that couldn't be read.
+        let filters = CollectFilters {
+            max_depth: self.max_depth,
+            extensions: self.extensions.clone(),
+            exclude_globs: self.exclude_globs.clone(),
+            max_file_size: self.max_file_size,
+            min_file_size: self.min_file_size,
+            since: self.since,
+            exclude_hidden: self.exclude_hidden,
+            files_only: self.files_only,
+        };
+        let (mut entries, dir_warnings, empty_dirs) = collect_file_entries(dirs, &filters);
+        *self.empty_dirs.lock().unwrap() = empty_dirs;
+        if let Some(sort) = self.sort {
+            sort_entries(&mut entries, sort);
+        }
+        let paths: Vec<String> = entries
+            .into_iter()
+            .map(|e| match self.path_style {
+                Some(style) => apply_path_style(&e.path, &e.root, style),
+                None => e.path,
+            })
+            .collect();
         let total = paths.len();
+        self.total_count.store(total, Ordering::SeqCst);
+        self.dispatched_count.store(0, Ordering::SeqCst);
+
+        for warning in dir_warnings {
+            let _ = tx.send(ProgressMessage::FileFailed(warning.filename.clone(), warning));
+        }
 
         // Clone shared state for the worker thread
         let tx_main = tx.clone();
-        let cancellation = Arc::clone(&self.cancellation);
         let total_bytes = Arc::clone(&self.total_bytes_processed);
         let pool_arc = Arc::clone(&self.pool);
         let persist_path = self.persist_path.clone();
+        let max_files_per_sec = self.max_files_per_sec;
+        let progress_interval = self.progress_interval;
+        let checkpoint_every = self.checkpoint_every.max(1);
+        let cache_path = self.cache_path.clone();
+        let cache = cache_path
+            .as_ref()
+            .map(|p| Arc::new(Mutex::new(Cache::load(p))));
+        let match_pattern = self.match_pattern.clone();
+        let timeout = self.timeout;
+        let retries = self.retries;
+        let paused = Arc::clone(&self.paused);
+        let dispatched_count = Arc::clone(&self.dispatched_count);
+        let max_total_bytes = self.max_total_bytes;
+        let on_complete = self.on_complete.clone();
+        let compute_hash = self.compute_hash;
+        let track_words = self.track_words;
+        let lossy = self.lossy;
+
+        // Minimum spacing between dispatches required to honor the rate cap
+        let min_interval = max_files_per_sec
+            .filter(|r| *r > 0.0)
+            .map(|r| time::Duration::from_secs_f64(1.0 / r));
 
         // Spawn a dispatcher thread that submits jobs to the pool
         thread::spawn(move || {
             let mut completed = 0usize;
+            let mut last_dispatch: Option<time::Instant> = None;
+            let mut last_progress_sent: Option<time::Instant> = None;
+            let mut last_progress_completed = 0usize;
 
             for path in paths {
                 // Stop processing if cancellation is requested
-                if cancellation.load(Ordering::SeqCst) {
+                if token.is_cancelled() {
+                    break;
+                }
+
+                // Block here while paused via the `control` interface,
+                // re-checking cancellation periodically so a cancel
+                // received while paused still takes effect promptly
+                // instead of waiting indefinitely for a `resume`.
+                {
+                    let mut guard = paused.0.lock().unwrap();
+                    while *guard && !token.is_cancelled() {
+                        let (next_guard, _) = paused
+                            .1
+                            .wait_timeout(guard, time::Duration::from_millis(50))
+                            .unwrap();
+                        guard = next_guard;
+                    }
+                }
+                if token.is_cancelled() {
+                    break;
+                }
+
+                // Check the cache before doing any real work: if the file's
+                // mtime and size match a cached entry, skip analysis and
+                // reuse the cached result.
+                let cache_hit = cache.as_ref().and_then(|cache_arc| {
+                    let (mtime_secs, size) = cache::file_cache_key(&path)?;
+                    cache_arc.lock().unwrap().get(&path, mtime_secs, size).cloned()
+                });
+
+                // Pace dispatch to honor `max_files_per_sec`, if configured.
+                // Cache hits do no real work, so they aren't paced.
+                // With no rate set, jobs are submitted as fast as the pool
+                // accepts them.
+                if cache_hit.is_none() && let Some(min_interval) = min_interval {
+                    if let Some(prev) = last_dispatch {
+                        let elapsed = prev.elapsed();
+                        if elapsed < min_interval {
+                            thread::sleep(min_interval - elapsed);
+                        }
+                    }
+                    last_dispatch = Some(time::Instant::now());
+                }
+
+                // Stop dispatching further files once the cumulative byte
+                // budget has been reached. Checked right before dispatch
+                // (rather than at the top of the loop) so it sees bytes
+                // from files that completed during the pacing sleep above,
+                // instead of a count that's perpetually a step stale.
+                if let Some(budget) = max_total_bytes
+                    && total_bytes.load(Ordering::SeqCst) as u64 >= budget
+                {
                     break;
                 }
 
                 // Notify that processing for this file has started
                 let tx_file = tx_main.clone();
+                tracing::debug!(file = %path, "file started");
                 tx_file
                     .send(ProgressMessage::FileStarted(path.clone()))
                     .ok();
 
-                let path_clone = path.clone();
-                let tx_submit = tx_file.clone();
-                let cancel_for_task = Arc::clone(&cancellation);
-                let bytes_for_task = Arc::clone(&total_bytes);
-
-                // Job executed by the thread pool
-                let job = move || {
-                    // Check for cancellation before starting work
-                    if cancel_for_task.load(Ordering::SeqCst) {
-                        let _ = tx_submit.send(ProgressMessage::FileFailed(
-                            path_clone.clone(),
-                            ProcessingError {
-                                filename: path_clone.clone(),
-                                operation: "cancelled".into(),
-                                message: "Cancelled before start".into(),
-                            },
+                if let Some(analysis) = cache_hit {
+                    // Cache hit: reuse the cached analysis without touching the pool.
+                    total_bytes.fetch_add(analysis.stats.bytes_read as usize, Ordering::SeqCst);
+                    if !analysis.errors.is_empty() {
+                        let _ = tx_file.send(ProgressMessage::FileWarning(
+                            analysis.filename.clone(),
+                            analysis.errors.clone(),
                         ));
-                        return;
                     }
+                    if let Some(hook) = &on_complete {
+                        hook(&analysis);
+                    }
+                    tracing::info!(file = %analysis.filename, "file completed");
+                    let _ = tx_file.send(ProgressMessage::FileCompleted(analysis));
+                } else {
+                    let path_clone = path.clone();
+                    let tx_submit = tx_file.clone();
+                    let cancel_for_task = token.clone();
+                    let bytes_for_task = Arc::clone(&total_bytes);
+                    let cache_for_task = cache.clone();
+                    let cache_path_for_task = cache_path.clone();
+                    let pattern_for_task = match_pattern.clone();
+                    let on_complete_for_task = on_complete.clone();
+                    let compute_hash_for_task = compute_hash;
+                    let track_words_for_task = track_words;
+                    let lossy_for_task = lossy;
 
-                    // Perform file analysis
-                    let analysis = analyze_file(&path_clone);
+                    // Job executed by the thread pool
+                    let job = move || {
+                        // Check for cancellation before starting work
+                        if cancel_for_task.is_cancelled() {
+                            tracing::warn!(file = %path_clone, "file failed: cancelled before start");
+                            let _ = tx_submit.send(ProgressMessage::FileFailed(
+                                path_clone.clone(),
+                                ProcessingError {
+                                    filename: path_clone.clone(),
+                                    operation: "cancelled".into(),
+                                    message: "Cancelled before start".into(),
+                                },
+                            ));
+                            return;
+                        }
 
-                    // Update total bytes processed atomically
-                    bytes_for_task.fetch_add(
-                        analysis.stats.size_bytes as usize,
-                        Ordering::SeqCst,
-                    );
+                        // Transparently expand tar archives into one
+                        // `FileCompleted` per regular-file entry, instead
+                        // of analyzing the archive itself as one blob.
+                        if is_tar_archive(&path_clone) {
+                            for analysis in analyze_tar_entries(&path_clone) {
+                                bytes_for_task.fetch_add(
+                                    analysis.stats.bytes_read as usize,
+                                    Ordering::SeqCst,
+                                );
+                                if !analysis.errors.is_empty() {
+                                    let _ = tx_submit.send(ProgressMessage::FileWarning(
+                                        analysis.filename.clone(),
+                                        analysis.errors.clone(),
+                                    ));
+                                }
+                                if let Some(hook) = &on_complete_for_task {
+                                    hook(&analysis);
+                                }
+                                tracing::info!(file = %analysis.filename, "file completed");
+                                let _ = tx_submit.send(ProgressMessage::FileCompleted(analysis));
+                            }
+                            return;
+                        }
 
-                    // Send completion message
-                    let _ = tx_submit.send(ProgressMessage::FileCompleted(analysis));
-                };
+                        // Perform file analysis, retrying up to `retries`
+                        // additional times if the attempt times out or comes
+                        // back with non-fatal errors.
+                        let mut attempt = analyze_with_timeout(&path_clone, timeout, lossy_for_task);
+                        let mut attempts_left = retries;
+                        while attempts_left > 0
+                            && attempt.as_ref().is_none_or(|a| !a.errors.is_empty())
+                        {
+                            attempts_left -= 1;
+                            attempt = analyze_with_timeout(&path_clone, timeout, lossy_for_task);
+                        }
 
-                // Submit job to the thread pool
-                let pool_guard = pool_arc.lock().unwrap();
-                pool_guard.execute(job);
+                        let mut analysis = match attempt {
+                            Some(analysis) => analysis,
+                            None => {
+                                tracing::warn!(file = %path_clone, "file failed: timed out");
+                                let _ = tx_submit.send(ProgressMessage::FileFailed(
+                                    path_clone.clone(),
+                                    ProcessingError {
+                                        filename: path_clone.clone(),
+                                        operation: "timeout".into(),
+                                        message: format!(
+                                            "Analysis did not complete within {:?}",
+                                            timeout.unwrap_or_default()
+                                        ),
+                                    },
+                                ));
+                                return;
+                            }
+                        };
 
-                completed += 1;
+                        // If a match pattern was configured, scan for matching
+                        // lines and fold the result into the analysis stats.
+                        if let Some(regex) = &pattern_for_task {
+                            let (count, matches) = scan_regex_matches(&path_clone, regex);
+                            analysis.stats.regex_match_count = Some(count);
+                            analysis.stats.regex_matches = Some(matches);
+                        }
 
-                // Send overall progress update
-                let _ = tx_main.send(ProgressMessage::OverallProgress {
-                    completed,
-                    total,
-                });
+                        // If hashing was requested (e.g. for the CLI's
+                        // `--dedup` mode), compute the file's content hash.
+                        if compute_hash_for_task {
+                            analysis.stats.content_hash = hash_file(&path_clone);
+                        }
 
-                // Persist progress to disk
-                let _ = persist_progress(&persist_path, completed, total);
+                        // If word-frequency aggregation was requested (e.g.
+                        // for the CLI's `--top-words` mode), build the
+                        // per-word occurrence map and pick the file's most
+                        // common word from it.
+                        if track_words_for_task {
+                            let freq = word_frequencies(&path_clone);
+                            analysis.stats.most_common_word = freq
+                                .iter()
+                                .fold(None, |best: Option<(String, usize)>, (word, &count)| {
+                                    match &best {
+                                        None => Some((word.clone(), count)),
+                                        Some((best_word, best_count)) => {
+                                            if count > *best_count
+                                                || (count == *best_count && word < best_word)
+                                            {
+                                                Some((word.clone(), count))
+                                            } else {
+                                                best
+                                            }
+                                        }
+                                    }
+                                });
+                            analysis.stats.word_frequencies = freq;
+                        }
+
+                        // Update total bytes processed atomically
+                        bytes_for_task.fetch_add(
+                            analysis.stats.bytes_read as usize,
+                            Ordering::SeqCst,
+                        );
+
+                        // Update the on-disk cache with the freshly computed result.
+                        if let Some(cache_arc) = &cache_for_task
+                            && let Some((mtime_secs, size)) = cache::file_cache_key(&path_clone)
+                        {
+                            let mut cache_guard = cache_arc.lock().unwrap();
+                            cache_guard.insert(path_clone.clone(), mtime_secs, size, analysis.clone());
+                            if let Some(cp) = &cache_path_for_task {
+                                let _ = cache_guard.save(cp);
+                            }
+                        }
+
+                        // Surface any non-fatal errors (e.g. a binary-fallback
+                        // trigger) before reporting completion.
+                        if !analysis.errors.is_empty() {
+                            let _ = tx_submit.send(ProgressMessage::FileWarning(
+                                analysis.filename.clone(),
+                                analysis.errors.clone(),
+                            ));
+                        }
 
-                // Small delay to avoid overwhelming the system
-                thread::sleep(time::Duration::from_millis(10));
+                        if let Some(hook) = &on_complete_for_task {
+                            hook(&analysis);
+                        }
+
+                        // Send completion message
+                        tracing::info!(file = %analysis.filename, "file completed");
+                        let _ = tx_submit.send(ProgressMessage::FileCompleted(analysis));
+                    };
+
+                    // Submit job to the thread pool
+                    let pool_guard = pool_arc.lock().unwrap();
+                    let submitted = pool_guard.execute(job);
+                    drop(pool_guard);
+                    if submitted.is_err() {
+                        tracing::warn!(file = %path, "file failed: pool rejected job");
+                        let _ = tx_file.send(ProgressMessage::FileFailed(
+                            path.clone(),
+                            ProcessingError {
+                                filename: path.clone(),
+                                operation: "pool_rejected".into(),
+                                message: "Thread pool is no longer accepting jobs".into(),
+                            },
+                        ));
+                    }
+                }
+
+                completed += 1;
+                dispatched_count.store(completed, Ordering::SeqCst);
+
+                // Coalesce overall progress updates: emit at most every
+                // `PROGRESS_COALESCE_FILES` files or `progress_interval`,
+                // whichever comes first, so large runs don't flood the channel.
+                // The final update (handled after the loop) always fires.
+                let due_by_count = completed - last_progress_completed >= PROGRESS_COALESCE_FILES;
+                let due_by_time = last_progress_sent
+                    .map(|t| t.elapsed() >= progress_interval)
+                    .unwrap_or(true);
+                if completed < total && (due_by_count || due_by_time) {
+                    let _ = tx_main.send(ProgressMessage::OverallProgress {
+                        completed,
+                        total,
+                    });
+                    last_progress_sent = Some(time::Instant::now());
+                    last_progress_completed = completed;
+                }
+
+                // Persist progress to disk every `checkpoint_every` files,
+                // rather than after each one, to avoid write amplification
+                // on large runs. The final checkpoint below always fires.
+                if completed.is_multiple_of(checkpoint_every) {
+                    tracing::debug!(completed, total, "checkpoint written");
+                    let _ = persist_progress(&persist_path, completed, total);
+                }
             }
+
+            // Always send a final update so listeners see 100% completion,
+            // and always write a final checkpoint regardless of interval.
+            let _ = tx_main.send(ProgressMessage::OverallProgress { completed, total });
+            tracing::debug!(completed, total, "checkpoint written");
+            let _ = persist_progress(&persist_path, completed, total);
         });
 
         rx
@@ -164,43 +1020,847 @@ impl FileProcessor {
     pub fn total_bytes_processed(&self) -> usize {
         self.total_bytes_processed.load(Ordering::SeqCst)
     }
+
+    /// Returns the directories found empty (no matching files anywhere in
+    /// their subtree) during the most recent collection, for auditing a
+    /// tree ahead of cleanup.
+    #[allow(dead_code)]
+    pub fn empty_dirs(&self) -> Vec<String> {
+        self.empty_dirs.lock().unwrap().clone()
+    }
+
+    /// Returns the highest number of jobs the underlying thread pool's
+    /// queue has held at once, as a saturation signal for the caller to
+    /// compare against the configured worker count.
+    pub fn pool_peak_queue_depth(&self) -> usize {
+        self.pool.lock().unwrap().peak_queue_depth()
+    }
+
+    /// Returns the average queue depth submitters saw when dispatching
+    /// jobs to the underlying thread pool.
+    pub fn pool_average_queue_depth(&self) -> f64 {
+        self.pool.lock().unwrap().average_queue_depth()
+    }
+
+    /// Processes a list of directories and returns an iterator over
+    /// progress updates, for callers that want to `for msg in
+    /// processor.process_dirs_iter(dirs)` instead of naming the channel
+    /// receiver. Iteration ends once every file has been dispatched and the
+    /// sender side of the channel is dropped, the same point at which a
+    /// `for msg in rx` loop over `process_dirs`'s receiver would end.
+    #[allow(dead_code)]
+    pub fn process_dirs_iter(&self, dirs: Vec<String>) -> impl Iterator<Item = ProgressMessage> {
+        self.process_dirs(dirs).into_iter()
+    }
+
+    /// Processes a list of directories and blocks until completion,
+    /// returning every result in a single `Vec` instead of streaming
+    /// progress over a channel. Intended for library consumers that want
+    /// the simplest possible embedding API.
+    #[allow(dead_code)]
+    pub fn process_dirs_collect(&self, dirs: Vec<String>) -> Vec<Result<FileAnalysis, ProcessingError>> {
+        let rx = self.process_dirs(dirs);
+        let mut results = Vec::new();
+
+        for msg in rx {
+            match msg {
+                ProgressMessage::FileCompleted(analysis) => results.push(Ok(analysis)),
+                ProgressMessage::FileFailed(_, err) => results.push(Err(err)),
+                ProgressMessage::FileStarted(_)
+                | ProgressMessage::FileWarning(_, _)
+                | ProgressMessage::OverallProgress { .. } => {}
+            }
+        }
+
+        results
+    }
+
+    /// Computes aggregate totals for `dirs` with `num_threads` workers,
+    /// each accumulating its own results in a thread-local `AggregateStats`
+    /// as files finish and merging into the shared total exactly once, when
+    /// it shuts down. This means the shared `Mutex` is locked `num_threads`
+    /// times total rather than once per file, and the totals are complete
+    /// the instant the last worker joins, with no reduce pass over a
+    /// channel of messages needed. Scoped to totals only (no caching, tar
+    /// expansion, or regex matching) since it spawns its own short-lived
+    /// worker threads rather than going through `process_dirs_with_token`.
+    #[allow(dead_code)]
+    pub fn process_dirs_aggregated(&self, dirs: Vec<String>, num_threads: usize) -> AggregateStats {
+        let filters = CollectFilters {
+            max_depth: self.max_depth,
+            extensions: self.extensions.clone(),
+            exclude_globs: self.exclude_globs.clone(),
+            max_file_size: self.max_file_size,
+            min_file_size: self.min_file_size,
+            since: self.since,
+            exclude_hidden: self.exclude_hidden,
+            files_only: self.files_only,
+        };
+        let (paths, _warnings, _empty_dirs) = collect_files(dirs, &filters);
+
+        let (job_tx, job_rx) = mpsc::channel::<String>();
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let shared = Arc::new(Mutex::new(AggregateStats::default()));
+        let timeout = self.timeout;
+        let retries = self.retries;
+
+        let handles: Vec<_> = (0..num_threads.max(1))
+            .map(|_| {
+                let job_rx = Arc::clone(&job_rx);
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || {
+                    // Local to this worker thread for its whole lifetime:
+                    // no lock is taken while files are being analyzed.
+                    let mut local = AggregateStats::default();
+
+                    loop {
+                        let path = {
+                            let rx = job_rx.lock().expect("job queue lock poisoned");
+                            rx.recv()
+                        };
+                        let Ok(path) = path else { break };
+
+                        local.files += 1;
+
+                        let mut attempt = analyze_with_timeout(&path, timeout, false);
+                        let mut attempts_left = retries;
+                        while attempts_left > 0
+                            && attempt.as_ref().is_none_or(|a| !a.errors.is_empty())
+                        {
+                            attempts_left -= 1;
+                            attempt = analyze_with_timeout(&path, timeout, false);
+                        }
+
+                        match attempt {
+                            Some(analysis) => {
+                                local.bytes += analysis.stats.bytes_read as usize;
+                                local.words += analysis.stats.word_count;
+                                local.lines += analysis.stats.line_count;
+                            }
+                            None => local.errors += 1,
+                        }
+                    }
+
+                    // Merge once, at shutdown, instead of locking per file.
+                    shared
+                        .lock()
+                        .expect("aggregate lock poisoned")
+                        .merge(&local);
+                })
+            })
+            .collect();
+
+        for path in paths {
+            let _ = job_tx.send(path);
+        }
+        drop(job_tx);
+
+        for handle in handles {
+            let _ = handle.join();
+        }
+
+        Arc::try_unwrap(shared)
+            .map(|m| m.into_inner().expect("aggregate lock poisoned"))
+            .unwrap_or_else(|arc| arc.lock().expect("aggregate lock poisoned").clone())
+    }
+
+    /// Runs an initial `process_dirs` pass over `dirs`, then keeps
+    /// watching them for created or modified files, re-analyzing each one
+    /// and sending a fresh `FileCompleted` for it. Rapid-fire events for
+    /// the same path within `debounce` are coalesced into a single
+    /// re-analysis. Unlike `process_dirs`, the returned channel is never
+    /// disconnected on its own; call `cancel` to stop the watch loop. Like
+    /// `process_dirs`, the channel has bounded capacity, so the caller
+    /// must keep draining it or the watch loop will stall on `send`.
+    #[allow(dead_code)]
+    pub fn process_dirs_watch(&self, dirs: Vec<String>) -> mpsc::Receiver<ProgressMessage> {
+        let (tx, rx) = mpsc::sync_channel::<ProgressMessage>(PROGRESS_CHANNEL_CAPACITY);
+
+        for msg in self.process_dirs(dirs.clone()) {
+            if tx.send(msg).is_err() {
+                return rx;
+            }
+        }
+
+        let cancellation = Arc::clone(&self.cancellation);
+        let debounce = self.watch_debounce;
+        thread::spawn(move || {
+            use notify::Watcher;
+
+            let (watch_tx, watch_rx) = mpsc::channel::<notify::Result<notify::Event>>();
+            let mut watcher = match notify::recommended_watcher(move |res| {
+                let _ = watch_tx.send(res);
+            }) {
+                Ok(w) => w,
+                Err(_) => return,
+            };
+            for dir in &dirs {
+                let _ = watcher.watch(Path::new(dir), notify::RecursiveMode::Recursive);
+            }
+
+            // Paths with a pending change and the time it was last seen,
+            // so a burst of writes to one file is coalesced into one
+            // re-analysis instead of one per event.
+            let mut pending: std::collections::HashMap<String, time::Instant> =
+                std::collections::HashMap::new();
+
+            loop {
+                if cancellation.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                match watch_rx.recv_timeout(debounce) {
+                    Ok(Ok(event)) => {
+                        if matches!(
+                            event.kind,
+                            notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+                        ) {
+                            for path in event.paths {
+                                if path.is_file() {
+                                    pending.insert(path.to_string_lossy().to_string(), time::Instant::now());
+                                }
+                            }
+                        }
+                    }
+                    Ok(Err(_)) => {}
+                    Err(mpsc::RecvTimeoutError::Timeout) => {}
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+
+                let now = time::Instant::now();
+                let ready: Vec<String> = pending
+                    .iter()
+                    .filter(|&(_, &last)| now.duration_since(last) >= debounce)
+                    .map(|(path, _)| path.clone())
+                    .collect();
+
+                for path in ready {
+                    pending.remove(&path);
+                    let analysis = analyze_file_chunked(&path);
+                    tracing::info!(file = %analysis.filename, "file completed");
+                    if tx.send(ProgressMessage::FileCompleted(analysis)).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        rx
+    }
 }
 
-/// Collects all files from the given list of directories or file paths.
+/// A file discovered by `collect_file_entries`, paired with the size and
+/// modification time read from its `DirEntry`/`fs::metadata` during the
+/// same traversal, so downstream filtering, sorting, and size-skipping
+/// don't need to re-`stat` a path `collect_file_entries` already looked at.
 ///
-/// - If a path is a file, it is added directly
-/// - If a path is a directory, all files in that directory are added
-fn collect_files(dirs: Vec<String>) -> Vec<String> {
+/// `root` is the entry in `dirs` this file was discovered under (itself,
+/// for a bare file argument), used by `set_path_style` to compute a path
+/// relative to the scanned root.
+#[derive(Debug, Clone)]
+struct FileEntry {
+    path: String,
+    size: u64,
+    modified: Option<time::SystemTime>,
+    root: String,
+}
+
+/// Controls how `filename` is rendered in progress messages and analysis
+/// results, set via `FileProcessor::set_path_style`. Bare filenames can
+/// collide and confuse output when scanning many directories at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStyle {
+    /// The absolute path, resolved via `std::path::absolute`.
+    Absolute,
+    /// The path relative to whichever `dirs` entry the file was
+    /// discovered under.
+    Relative,
+    /// Just the filename, discarding all directory components.
+    Basename,
+}
+
+/// Renders `path` (discovered under `root`) according to `style`. Falls
+/// back to the original `path` unchanged when the requested
+/// transformation doesn't apply, e.g. `Absolute` if the path can no
+/// longer be resolved, or `Relative`/`Basename` if `path` has no parent
+/// or file-name component.
+fn apply_path_style(path: &str, root: &str, style: PathStyle) -> String {
+    match style {
+        PathStyle::Absolute => std::path::absolute(path)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string()),
+        PathStyle::Relative => Path::new(path)
+            .strip_prefix(root)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| path.to_string()),
+        PathStyle::Basename => Path::new(path)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string()),
+    }
+}
+
+/// Filters applied while collecting files: how deep to descend, which
+/// extensions to keep, which glob patterns to exclude, and the smallest
+/// and largest file sizes to collect.
+struct CollectFilters {
+    max_depth: Option<usize>,
+    extensions: Option<Vec<String>>,
+    exclude_globs: Vec<Regex>,
+    max_file_size: Option<u64>,
+    min_file_size: Option<u64>,
+    since: Option<time::SystemTime>,
+    exclude_hidden: bool,
+
+    /// When `true`, `collect_files` rejects any `dirs` entry that isn't a
+    /// regular file instead of expanding directories.
+    files_only: bool,
+}
+
+/// Returns `true` if `path`'s file name starts with `.`, Unix-dotfile
+/// style (`.git`, `.DS_Store`, etc.).
+fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.starts_with('.'))
+}
+
+/// Returns whether `path` survives `filters`' extension allow-list,
+/// exclude-glob patterns, and size range (depth is handled separately by
+/// the caller, since it applies to directories, not individual files).
+/// `size` is the file's length, read once by the caller during traversal
+/// rather than re-`stat`ed here. `bypass_exclude` skips the exclude-glob
+/// check entirely, for files under a directory that was passed directly
+/// to `collect_files` (an explicit include always wins over a glob
+/// exclusion).
+fn passes_filters(path: &Path, size: u64, filters: &CollectFilters, bypass_exclude: bool) -> bool {
+    if filters.exclude_hidden && is_hidden(path) {
+        return false;
+    }
+
+    if let Some(extensions) = &filters.extensions {
+        let has_allowed_extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| extensions.iter().any(|allowed| allowed == ext));
+        if !has_allowed_extension {
+            return false;
+        }
+    }
+
+    if !bypass_exclude {
+        let path_str = path.to_string_lossy();
+        if filters.exclude_globs.iter().any(|re| re.is_match(&path_str)) {
+            return false;
+        }
+    }
+
+    if let Some(max_size) = filters.max_file_size
+        && size > max_size
+    {
+        return false;
+    }
+
+    if let Some(min_size) = filters.min_file_size
+        && size < min_size
+    {
+        return false;
+    }
+
+    true
+}
+
+/// Returns whether `path` survives `filters.since`: a file modified
+/// strictly after the cutoff passes, one at or before it doesn't. `modified`
+/// is the file's modification time, read once by the caller during
+/// traversal rather than re-`stat`ed here. A file whose modification time
+/// couldn't be read is let through anyway (with a warning pushed to
+/// `warnings`) rather than silently dropped, since an unreadable mtime is
+/// surprising and not evidence the file is unchanged.
+fn passes_since_filter(
+    path: &Path,
+    modified: Option<time::SystemTime>,
+    filters: &CollectFilters,
+    warnings: &mut Vec<ProcessingError>,
+) -> bool {
+    let Some(since) = filters.since else {
+        return true;
+    };
+
+    match modified {
+        Some(mtime) => mtime > since,
+        None => {
+            warnings.push(ProcessingError {
+                filename: path.to_string_lossy().to_string(),
+                operation: "since".to_string(),
+                message: "could not read modification time".to_string(),
+            });
+            true
+        }
+    }
+}
+
+/// Sorts `entries` in place by `order`, so dispatch happens in a
+/// reproducible sequence instead of whatever order `read_dir` happened to
+/// return (which varies by platform and filesystem). Reuses the size and
+/// modification time `collect_file_entries` already captured instead of
+/// re-`stat`ing each path.
+fn sort_entries(entries: &mut [FileEntry], order: SortOrder) {
+    match order {
+        SortOrder::Name => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+        SortOrder::Size => entries.sort_by_key(|e| e.size),
+        SortOrder::Mtime => entries.sort_by_key(|e| e.modified.unwrap_or(time::UNIX_EPOCH)),
+    }
+}
+
+/// Collects all files from the given list of directories or file paths,
+/// descending at most `filters.max_depth` levels below each directory
+/// (`None` means unlimited; `Some(0)` collects only each directory's
+/// immediate files) and applying `filters`' extension/glob/size rules,
+/// along with a `ProcessingError` for each directory that couldn't be read
+/// (e.g. permission denied, operation `"read_dir_root"` for one of the
+/// `dirs` themselves or `"read_dir"` for a nested subdirectory) or each
+/// entry in `dirs` that is neither a file nor a directory (operation
+/// `"not_found"`, most likely a typo), so callers can surface the problem
+/// instead of silently reporting a short `total`.
+///
+/// A directory passed directly in `dirs` is treated as an explicit
+/// include: its files (and those of its subdirectories) are exempt from
+/// `filters.exclude_globs`, though still subject to the extension and
+/// size filters. This lets a user override a global exclude glob for a
+/// specific subtree by naming it on the command line.
+///
+/// Each returned `FileEntry` carries the size and modification time read
+/// from its directory entry during this same walk, so callers like
+/// `sort_entries` and size-based filtering don't need to re-`stat` the
+/// path. The third element of the returned tuple lists every directory
+/// visited that contained no matching files anywhere in its subtree, for
+/// `--include-empty-dirs`-style cleanup audits.
+fn collect_file_entries(
+    dirs: Vec<String>,
+    filters: &CollectFilters,
+) -> (Vec<FileEntry>, Vec<ProcessingError>, Vec<String>) {
     let mut files = Vec::new();
+    let mut warnings = Vec::new();
+    let mut empty_dirs = Vec::new();
 
     for d in dirs {
         let p = Path::new(&d);
 
-        if p.is_file() {
-            files.push(d.clone());
-        } else if p.is_dir() {
-            if let Ok(entries) = fs::read_dir(p) {
-                for entry in entries.flatten() {
-                    let path = entry.path();
+        match fs::metadata(p) {
+            Ok(metadata) if metadata.is_file() => {
+                let size = metadata.len();
+                let modified = metadata.modified().ok();
+                if passes_filters(p, size, filters, false)
+                    && passes_since_filter(p, modified, filters, &mut warnings)
+                {
+                    files.push(FileEntry { path: d.clone(), size, modified, root: d.clone() });
+                }
+            }
+            Ok(metadata) if metadata.is_dir() => {
+                if filters.files_only {
+                    // `--files-only` skips directory expansion entirely:
+                    // anything that isn't a regular file is reported as an
+                    // error rather than silently descended into.
+                    warnings.push(ProcessingError {
+                        filename: d.clone(),
+                        operation: "not_a_file".to_string(),
+                        message: format!("{:?} is not a regular file (--files-only is set)", d),
+                    });
+                } else {
+                    visit_dir(p, 0, &d, filters, &mut files, &mut warnings, &mut empty_dirs, true);
+                }
+            }
+            _ => {
+                // Neither a file nor a directory: most likely a typo in the
+                // argument. Surface it instead of silently contributing zero
+                // files, so a bad path doesn't look like an empty-but-valid run.
+                warnings.push(ProcessingError {
+                    filename: d.clone(),
+                    operation: "not_found".to_string(),
+                    message: format!("{:?} is not a file or directory", d),
+                });
+            }
+        }
+    }
+
+    (files, warnings, empty_dirs)
+}
+
+/// Thin wrapper around `collect_file_entries` for callers that only need
+/// paths, not the size/modification-time metadata gathered alongside them.
+fn collect_files(
+    dirs: Vec<String>,
+    filters: &CollectFilters,
+) -> (Vec<String>, Vec<ProcessingError>, Vec<String>) {
+    let (entries, warnings, empty_dirs) = collect_file_entries(dirs, filters);
+    (entries.into_iter().map(|e| e.path).collect(), warnings, empty_dirs)
+}
+
+/// Recursively visits `dir`, adding its files to `files` and descending
+/// into subdirectories while `depth` is still below `filters.max_depth`.
+/// `depth` is `0` for the directory originally passed to `collect_files`,
+/// `1` for its subdirectories, and so on. `bypass_exclude` is `true` for
+/// the explicit-include subtree rooted at a directory passed directly to
+/// `collect_files` (see its doc comment) and is carried down unchanged to
+/// every descendant.
+///
+/// Returns the number of matching files found in `dir`'s subtree; `dir`
+/// itself is pushed onto `empty_dirs` when that count is zero, so a
+/// directory containing only empty subdirectories is reported too, not
+/// just directories with no entries at all. A directory that couldn't be
+/// read is reported as a warning instead, and not also counted as empty,
+/// tagged `read_dir_root` at `depth == 0` (one of the directories passed
+/// directly to `collect_files`) and plain `read_dir` for a nested
+/// subdirectory discovered during the walk, so `--strict-roots` can
+/// single out a bad top-level root without tripping on an unreadable
+/// subdirectory elsewhere in an otherwise-good tree.
+#[allow(clippy::too_many_arguments)]
+fn visit_dir(
+    dir: &Path,
+    depth: usize,
+    root: &str,
+    filters: &CollectFilters,
+    files: &mut Vec<FileEntry>,
+    warnings: &mut Vec<ProcessingError>,
+    empty_dirs: &mut Vec<String>,
+    bypass_exclude: bool,
+) -> usize {
+    let files_before = files.len();
+
+    match fs::read_dir(dir) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                let path = entry.path();
 
-                    if path.is_file() {
-                        if let Some(s) = path.to_str() {
-                            files.push(s.to_string());
+                match entry.metadata() {
+                    Ok(metadata) if metadata.is_file() => {
+                        let size = metadata.len();
+                        let modified = metadata.modified().ok();
+                        if passes_filters(&path, size, filters, bypass_exclude)
+                            && passes_since_filter(&path, modified, filters, warnings)
+                            && let Some(s) = path.to_str()
+                        {
+                            files.push(FileEntry {
+                                path: s.to_string(),
+                                size,
+                                modified,
+                                root: root.to_string(),
+                            });
                         }
                     }
+                    Ok(metadata)
+                        if metadata.is_dir() && filters.max_depth.is_none_or(|max| depth < max) =>
+                    {
+                        if filters.exclude_hidden && is_hidden(&path) {
+                            continue;
+                        }
+                        visit_dir(&path, depth + 1, root, filters, files, warnings, empty_dirs, bypass_exclude);
+                    }
+                    _ => {}
                 }
             }
         }
+        Err(e) => {
+            let operation = if depth == 0 { "read_dir_root" } else { "read_dir" };
+            warnings.push(ProcessingError {
+                filename: dir.to_string_lossy().to_string(),
+                operation: operation.to_string(),
+                message: e.to_string(),
+            });
+            return 0;
+        }
     }
 
-    files
+    let found = files.len() - files_before;
+    if found == 0 {
+        empty_dirs.push(dir.to_string_lossy().to_string());
+    }
+    found
+}
+
+/// Returns `true` if `path` should be transparently expanded as a tar
+/// archive, rather than analyzed as a single opaque file.
+fn is_tar_archive(path: &str) -> bool {
+    path.ends_with(".tar") || path.ends_with(".tar.gz")
+}
+
+/// Expands a tar archive (optionally gzip-compressed) into one
+/// `FileAnalysis` per regular-file entry, named `{archive_path}!{entry_path}`.
+/// Directories and other special entries (symlinks, hard links, devices,
+/// etc.) are skipped. A failure to open the archive or read an entry's
+/// header is reported as its own `FileAnalysis` carrying a
+/// `ProcessingError`, rather than silently dropping it.
+fn analyze_tar_entries(path: &str) -> Vec<FileAnalysis> {
+    let file = match fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) => return vec![failed_tar_analysis(path, "open", &e.to_string())],
+    };
+
+    let reader: Box<dyn std::io::Read> = if path.ends_with(".gz") {
+        Box::new(flate2::read::GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    let mut archive = tar::Archive::new(reader);
+    let entries = match archive.entries() {
+        Ok(entries) => entries,
+        Err(e) => return vec![failed_tar_analysis(path, "read_tar", &e.to_string())],
+    };
+
+    let mut analyses = Vec::new();
+    for entry in entries {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                analyses.push(failed_tar_analysis(path, "read_tar_entry", &e.to_string()));
+                continue;
+            }
+        };
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let entry_path = match entry.path() {
+            Ok(p) => p.to_string_lossy().to_string(),
+            Err(e) => {
+                analyses.push(failed_tar_analysis(path, "read_tar_entry", &e.to_string()));
+                continue;
+            }
+        };
+
+        let size = entry.header().size().unwrap_or(0);
+        let filename = format!("{}!{}", path, entry_path);
+        analyses.push(analyze_reader(&filename, &mut entry, size));
+    }
+
+    analyses
+}
+
+/// Builds a single-error `FileAnalysis` for tar-handling failures that
+/// prevent producing any real per-entry results.
+fn failed_tar_analysis(path: &str, operation: &str, message: &str) -> FileAnalysis {
+    FileAnalysis {
+        filename: path.to_string(),
+        stats: crate::analyzer::FileStats::new(),
+        errors: vec![ProcessingError {
+            filename: path.to_string(),
+            operation: operation.to_string(),
+            message: message.to_string(),
+        }],
+        processing_time: time::Duration::default(),
+        cpu_time: None,
+        sample_lines: Vec::new(),
+    }
 }
 
-/// Persists overall progress to disk as a JSON file.
+/// Persists overall progress to disk as a JSON file, writing to a
+/// temporary file and renaming it over `path` so a reader never observes a
+/// partially-written file. `fs::rename` is atomic when `{path}.tmp` and
+/// `path` are on the same filesystem; if they aren't (e.g. `path` points
+/// across a mount boundary), the rename fails with `ErrorKind::CrossesDevices`
+/// and this falls back to a direct, non-atomic write.
 ///
 /// Example output:
-/// `{ "completed": 5, "total": 20 }`
+/// `{ "version": 1, "completed": 5, "total": 20 }`
 fn persist_progress(path: &str, completed: usize, total: usize) -> std::io::Result<()> {
-    let json = format!(r#"{{"completed":{},"total":{}}}"#, completed, total);
-    std::fs::write(path, json)
+    let state = ProgressState {
+        version: PROGRESS_STATE_VERSION,
+        completed,
+        total,
+    };
+    let json = serde_json::to_string(&state).unwrap_or_default();
+    let tmp_path = format!("{}.tmp", path);
+    std::fs::write(&tmp_path, &json)?;
+    if let Err(e) = std::fs::rename(&tmp_path, path) {
+        if e.kind() == std::io::ErrorKind::CrossesDevices {
+            let _ = std::fs::remove_file(&tmp_path);
+            return std::fs::write(path, json);
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Reads back a progress file written by `persist_progress`, rejecting
+/// it with a clear error if it's missing, malformed, or was written by
+/// an incompatible schema version rather than silently misparsing it
+/// or resuming with wrong completed/total counts.
+#[allow(dead_code)]
+pub fn load_progress(path: &str) -> Result<ProgressState, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("failed to read progress file {}: {}", path, e))?;
+    let state: ProgressState = serde_json::from_str(&contents)
+        .map_err(|e| format!("failed to parse progress file {}: {}", path, e))?;
+    if state.version != PROGRESS_STATE_VERSION {
+        return Err(format!(
+            "unsupported progress file version {} in {} (expected {})",
+            state.version, path, PROGRESS_STATE_VERSION
+        ));
+    }
+    Ok(state)
+}
+
+/// Compiles a shell-style glob pattern (`*` matches any run of
+/// characters, `?` matches a single character) into an anchored `Regex`,
+/// for use by `FileProcessorBuilder::exclude_globs`. Returns `None` if the
+/// pattern can't be compiled.
+#[allow(dead_code)]
+fn glob_to_regex(pattern: &str) -> Option<Regex> {
+    let mut regex_str = String::from("^");
+    for ch in pattern.chars() {
+        match ch {
+            '*' => regex_str.push_str(".*"),
+            '?' => regex_str.push('.'),
+            c if "\\.+()|[]{}^$".contains(c) => {
+                regex_str.push('\\');
+                regex_str.push(c);
+            }
+            c => regex_str.push(c),
+        }
+    }
+    regex_str.push('$');
+    Regex::new(&regex_str).ok()
+}
+
+/// Runs `analyze_file_chunked` for `path` on a separate thread, enforcing
+/// `timeout` if set. Returns `None` if the analysis doesn't complete in
+/// time. `analyze_file_chunked` can't be preempted mid-read, so a timed-out
+/// analysis thread is simply left detached to finish (or not) on its own;
+/// its result is discarded once the channel's receiver is dropped.
+///
+/// When `lossy` is set, bypasses `analyze_file_chunked`'s chunked-parallel
+/// fast path entirely and calls `analyze_file_with_options` with
+/// `AnalyzeOptions::lossy_utf8` instead, so a file that isn't valid UTF-8
+/// is decoded lossily and analyzed as text rather than dropping to the
+/// raw-byte fallback.
+fn analyze_with_timeout(
+    path: &str,
+    timeout: Option<time::Duration>,
+    lossy: bool,
+) -> Option<FileAnalysis> {
+    let analyze = move |path: &str| {
+        if lossy {
+            analyze_file_with_options(
+                path,
+                &AnalyzeOptions {
+                    lossy_utf8: true,
+                    ..AnalyzeOptions::default()
+                },
+            )
+        } else {
+            analyze_file_chunked(path)
+        }
+    };
+
+    let Some(timeout) = timeout else {
+        return Some(analyze(path));
+    };
+
+    let (tx, rx) = mpsc::channel();
+    let path = path.to_string();
+    thread::spawn(move || {
+        let _ = tx.send(analyze(&path));
+    });
+
+    rx.recv_timeout(timeout).ok()
+}
+
+/// Builds a [`FileProcessor`] with optional configuration, so call sites
+/// that only need a couple of non-default settings don't have to thread
+/// every setter through by hand. `FileProcessor::new` remains the plain
+/// constructor for the common case of default settings; `build` starts
+/// from `FileProcessor::new(pool, path)` and applies each configured
+/// option on top of it.
+#[derive(Debug, Default, Clone)]
+#[allow(dead_code)]
+pub struct FileProcessorBuilder {
+    persist_path: String,
+    recursive: bool,
+    extensions: Option<Vec<String>>,
+    exclude_globs: Vec<String>,
+    max_file_size: Option<u64>,
+    min_file_size: Option<u64>,
+    timeout: Option<time::Duration>,
+    retries: usize,
+}
+
+#[allow(dead_code)]
+impl FileProcessorBuilder {
+    /// Creates a builder that will persist progress to `persist_path`, with
+    /// every other setting at its default.
+    pub fn new(persist_path: impl Into<String>) -> Self {
+        FileProcessorBuilder {
+            persist_path: persist_path.into(),
+            recursive: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether directory traversal should descend into subdirectories.
+    /// `false` is equivalent to `FileProcessor::set_max_depth(Some(0))`.
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Restricts collection to files whose extension (without the leading
+    /// dot) appears in `extensions`.
+    pub fn extensions(mut self, extensions: Vec<String>) -> Self {
+        self.extensions = Some(extensions);
+        self
+    }
+
+    /// Sets glob patterns (e.g. `*.log`, matched against the full path) for
+    /// files to exclude from collection.
+    pub fn exclude_globs(mut self, patterns: Vec<String>) -> Self {
+        self.exclude_globs = patterns;
+        self
+    }
+
+    /// Sets the maximum file size, in bytes, that will be collected for
+    /// analysis.
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = Some(max_file_size);
+        self
+    }
+
+    /// Sets the minimum file size, in bytes, that will be collected for
+    /// analysis, complementing `max_file_size`.
+    pub fn min_file_size(mut self, min_file_size: u64) -> Self {
+        self.min_file_size = Some(min_file_size);
+        self
+    }
+
+    /// Sets the maximum wall-clock time allowed to analyze a single file
+    /// before it's reported as failed with operation `"timeout"`.
+    pub fn timeout(mut self, timeout: time::Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the number of additional attempts made to analyze a file after
+    /// it comes back with non-fatal errors or times out, before giving up.
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Consumes the builder, producing a configured `FileProcessor` backed
+    /// by `pool`.
+    pub fn build(self, pool: ThreadPool) -> FileProcessor {
+        let mut processor = FileProcessor::new(pool, self.persist_path);
+        if !self.recursive {
+            processor.set_max_depth(Some(0));
+        }
+        processor.set_extensions(self.extensions);
+        processor.set_exclude_globs(&self.exclude_globs);
+        processor.set_max_file_size(self.max_file_size);
+        processor.set_min_file_size(self.min_file_size);
+        processor.set_timeout(self.timeout);
+        processor.set_retries(self.retries);
+        processor
+    }
 }
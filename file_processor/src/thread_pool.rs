@@ -3,20 +3,154 @@
 // This module implements a basic thread pool using worker threads
 // and message passing. Jobs are submitted to the pool through a channel
 // and executed by worker threads in a FIFO manner.
+//
+// The job channel itself has two backends, selected at compile time by the
+// `crossbeam` feature: `std::sync::mpsc`, whose single `Receiver` must be
+// shared across workers behind a `Mutex` (every worker locks the same
+// mutex to `recv`), or `crossbeam-channel`, whose `Receiver` is natively
+// multi-consumer and `Clone`, avoiding that contention point under many
+// workers. Both are wired up through the `JobSender`/`JobReceiver` aliases
+// and `new_channel`/`recv_job` below so the rest of the pool doesn't need
+// to know which one is active.
 
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(not(feature = "crossbeam"))]
+use std::sync::mpsc;
+
+/// How often an idle worker wakes from `recv_timeout` to perform
+/// housekeeping (checking `shutdown_flag`, recording idle time) even when
+/// no job has arrived.
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Sending half of the job channel; see the module-level doc comment for
+/// why this is one of two backends selected by the `crossbeam` feature.
+#[cfg(not(feature = "crossbeam"))]
+type JobSender = mpsc::Sender<Message>;
+#[cfg(feature = "crossbeam")]
+type JobSender = crossbeam_channel::Sender<Message>;
+
+/// Receiving half of the job channel, shared across workers. The std
+/// backend needs a `Mutex` since `mpsc::Receiver` isn't `Clone`; the
+/// crossbeam backend's `Receiver` is natively multi-consumer and `Clone`,
+/// so no `Mutex` (or `Arc`) wrapping is needed (either way
+/// `JobReceiver::clone` in the code below hands each worker its own
+/// handle, so call sites don't need to know which case they're in).
+#[cfg(not(feature = "crossbeam"))]
+type JobReceiver = Arc<Mutex<mpsc::Receiver<Message>>>;
+#[cfg(feature = "crossbeam")]
+type JobReceiver = crossbeam_channel::Receiver<Message>;
+
+/// Creates a fresh job channel using whichever backend the `crossbeam`
+/// feature selects.
+fn new_channel() -> (JobSender, JobReceiver) {
+    #[cfg(not(feature = "crossbeam"))]
+    {
+        let (tx, rx) = mpsc::channel();
+        (tx, Arc::new(Mutex::new(rx)))
+    }
+    #[cfg(feature = "crossbeam")]
+    {
+        crossbeam_channel::unbounded()
+    }
+}
+
+/// What a worker's blocking receive produced: a message, a housekeeping
+/// timeout with nothing to do, or a disconnected channel. Bridges
+/// `mpsc::RecvTimeoutError` and `crossbeam_channel::RecvTimeoutError`,
+/// which are equivalent but distinct types, behind one enum so the worker
+/// loop doesn't need a `cfg` of its own.
+enum RecvOutcome {
+    Job(Message),
+    Timeout,
+    Disconnected,
+}
+
+/// Blocks on `receiver` for up to `HOUSEKEEPING_INTERVAL`, using whichever
+/// channel backend the `crossbeam` feature selects.
+fn recv_job(receiver: &JobReceiver) -> RecvOutcome {
+    #[cfg(not(feature = "crossbeam"))]
+    {
+        let lock = receiver.lock().expect("Receiver lock poisoned");
+        match lock.recv_timeout(HOUSEKEEPING_INTERVAL) {
+            Ok(message) => RecvOutcome::Job(message),
+            Err(mpsc::RecvTimeoutError::Timeout) => RecvOutcome::Timeout,
+            Err(mpsc::RecvTimeoutError::Disconnected) => RecvOutcome::Disconnected,
+        }
+    }
+    #[cfg(feature = "crossbeam")]
+    {
+        match receiver.recv_timeout(HOUSEKEEPING_INTERVAL) {
+            Ok(message) => RecvOutcome::Job(message),
+            Err(crossbeam_channel::RecvTimeoutError::Timeout) => RecvOutcome::Timeout,
+            Err(crossbeam_channel::RecvTimeoutError::Disconnected) => RecvOutcome::Disconnected,
+        }
+    }
+}
 
 /// A thread pool that manages a fixed number of worker threads.
 ///
 /// Jobs are submitted via a channel and executed by the workers.
 /// The pool supports graceful shutdown through a shutdown message.
 pub struct ThreadPool {
-    /// Worker threads owned by the pool
-    workers: Vec<Worker>,
+    /// Worker threads owned by the pool. Behind a `Mutex` so `execute` (which
+    /// only takes `&self`) can push a freshly respawned worker after an idle
+    /// one has shrunk the pool.
+    workers: Mutex<Vec<Worker>>,
+
+    /// Target number of live workers, as originally passed to `new`. Used
+    /// by `execute` to decide how many workers to respawn after idle
+    /// shutdowns have shrunk the pool below this size.
+    target_size: usize,
+
+    /// Number of worker threads currently alive, decremented when a worker
+    /// exits after sitting idle past `idle_timeout`, and incremented again
+    /// when `execute` respawns one. Never allowed to drop below 1.
+    active_workers: Arc<AtomicUsize>,
+
+    /// How long a worker may sit idle (no job picked up) before it exits on
+    /// its own, shrinking the pool. `None` (the default) disables idle
+    /// shutdown entirely, matching the pool's original fixed-size behavior.
+    /// Shared so `set_idle_timeout` can change it after construction.
+    idle_timeout: Arc<Mutex<Option<Duration>>>,
 
     /// Sender side of the job queue channel
-    sender: Option<mpsc::Sender<Message>>,
+    sender: Option<JobSender>,
+
+    /// Receiver side of the job queue channel, shared across workers.
+    /// Retained so `execute` can hand a clone to a freshly respawned worker
+    /// after idle shutdown has shrunk the pool.
+    receiver: JobReceiver,
+
+    /// Shared out-of-band shutdown signal. Workers poll this on every
+    /// `recv_timeout` wakeup, so setting it (via `shutdown_flag`) causes
+    /// idle workers to exit without needing a `Shutdown` message.
+    shutdown_flag: Arc<AtomicBool>,
+
+    /// Total number of housekeeping wakeups (i.e. `recv_timeout` timeouts)
+    /// across all workers, as a rough idle-time measure.
+    idle_ticks: Arc<AtomicUsize>,
+
+    /// Number of jobs currently sitting in the channel, waiting for a
+    /// worker to pick them up. Incremented by `execute`, decremented as
+    /// each worker dequeues a job.
+    queued: Arc<AtomicUsize>,
+
+    /// Highest value `queued` has ever reached, as a saturation signal:
+    /// a queue that consistently backs up means the pool is undersized
+    /// for the submission rate.
+    peak_queued: Arc<AtomicUsize>,
+
+    /// Running sum of `queued` sampled at each `execute` call, divided by
+    /// `submit_count` to give the average queue depth a submitter saw.
+    queued_sum: Arc<AtomicUsize>,
+
+    /// Total number of jobs submitted via `execute`, used as the
+    /// denominator for `average_queue_depth`.
+    submit_count: Arc<AtomicUsize>,
 }
 
 /// A job is a boxed closure that can be executed once
@@ -31,6 +165,20 @@ enum Message {
     Shutdown,
 }
 
+/// Returned by `ThreadPool::execute` when the pool has stopped accepting
+/// new jobs (after `shutdown` or `stop_accepting`), so the submitted job
+/// was never queued and will never run.
+#[derive(Debug)]
+pub struct JobRejected;
+
+impl std::fmt::Display for JobRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "thread pool is no longer accepting jobs")
+    }
+}
+
+impl std::error::Error for JobRejected {}
+
 impl ThreadPool {
     /// Creates a new thread pool with the specified number of workers
     ///
@@ -40,33 +188,161 @@ impl ThreadPool {
         assert!(size > 0);
 
         // Create a channel for sending jobs to workers
-        let (sender, receiver) = mpsc::channel::<Message>();
+        let (sender, receiver) = new_channel();
 
-        // Share the receiver across all worker threads
-        let receiver = Arc::new(Mutex::new(receiver));
+        let shutdown_flag = Arc::new(AtomicBool::new(false));
+        let idle_ticks = Arc::new(AtomicUsize::new(0));
+        let queued = Arc::new(AtomicUsize::new(0));
+        let active_workers = Arc::new(AtomicUsize::new(size));
+        let idle_timeout = Arc::new(Mutex::new(None));
 
         let mut workers = Vec::with_capacity(size);
 
         // Spawn worker threads
         for id in 0..size {
-            workers.push(Worker::new(id, Arc::clone(&receiver)));
+            workers.push(Worker::new(
+                id,
+                receiver.clone(),
+                Arc::clone(&shutdown_flag),
+                Arc::clone(&idle_ticks),
+                Arc::clone(&queued),
+                Arc::clone(&active_workers),
+                Arc::clone(&idle_timeout),
+            ));
         }
 
         ThreadPool {
-            workers,
+            workers: Mutex::new(workers),
+            target_size: size,
+            active_workers,
+            idle_timeout,
             sender: Some(sender),
+            receiver,
+            shutdown_flag,
+            idle_ticks,
+            queued,
+            peak_queued: Arc::new(AtomicUsize::new(0)),
+            queued_sum: Arc::new(AtomicUsize::new(0)),
+            submit_count: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Sets how long an idle worker (no job picked up) may sit before it
+    /// exits on its own, shrinking the pool down to as few as one worker.
+    /// `execute` respawns workers back up to the original size on demand
+    /// once more jobs arrive. Pass `None` to disable idle shutdown.
+    #[allow(dead_code)]
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) {
+        *self.idle_timeout.lock().unwrap() = timeout;
+    }
+
+    /// Returns the number of worker threads currently alive, which may be
+    /// less than the pool's original size if idle shutdown has shrunk it.
+    #[allow(dead_code)]
+    pub fn worker_count(&self) -> usize {
+        self.active_workers.load(Ordering::SeqCst)
+    }
+
+    /// Returns a clone of the pool's shutdown flag, so external code can
+    /// signal idle workers to exit out-of-band (i.e. without going through
+    /// `shutdown`/`stop_accepting`) by setting it to `true`. Workers notice
+    /// the flag within roughly `HOUSEKEEPING_INTERVAL` of it being set.
+    #[allow(dead_code)]
+    pub fn shutdown_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.shutdown_flag)
+    }
+
+    /// Returns the number of housekeeping wakeups (`recv_timeout` timeouts)
+    /// observed across all workers so far, as a rough idle-time measure.
+    #[allow(dead_code)]
+    pub fn idle_ticks(&self) -> usize {
+        self.idle_ticks.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of jobs currently waiting in the queue for a
+    /// worker to pick up.
+    #[allow(dead_code)]
+    pub fn queued_count(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Returns the highest queue depth ever observed, as a saturation
+    /// signal: a peak that consistently exceeds the worker count means
+    /// jobs are piling up faster than they can be drained.
+    pub fn peak_queue_depth(&self) -> usize {
+        self.peak_queued.load(Ordering::Relaxed)
+    }
+
+    /// Returns the average queue depth seen by submitters at the moment
+    /// they called `execute`, or `0.0` if nothing has been submitted yet.
+    pub fn average_queue_depth(&self) -> f64 {
+        let submits = self.submit_count.load(Ordering::Relaxed);
+        if submits == 0 {
+            0.0
+        } else {
+            self.queued_sum.load(Ordering::Relaxed) as f64 / submits as f64
         }
     }
 
     /// Submits a job to the thread pool for execution
-    pub fn execute<F>(&self, f: F)
+    ///
+    /// Returns `Err(JobRejected)` if the pool is no longer accepting new
+    /// jobs (after `shutdown` or `stop_accepting`), instead of silently
+    /// dropping the job.
+    pub fn execute<F>(&self, f: F) -> Result<(), JobRejected>
     where
         F: FnOnce() + Send + 'static,
     {
         // Only submit jobs if the pool is still active
         if let Some(sender) = &self.sender {
             let job = Box::new(f);
-            let _ = sender.send(Message::NewJob(job));
+
+            // Increment before sending, so a worker can never observe and
+            // decrement a queue depth that hasn't been accounted for yet.
+            let depth = self.queued.fetch_add(1, Ordering::Relaxed) + 1;
+            self.peak_queued.fetch_max(depth, Ordering::Relaxed);
+            self.queued_sum.fetch_add(depth, Ordering::Relaxed);
+            self.submit_count.fetch_add(1, Ordering::Relaxed);
+
+            if sender.send(Message::NewJob(job)).is_err() {
+                self.queued.fetch_sub(1, Ordering::Relaxed);
+                return Err(JobRejected);
+            }
+
+            // If idle shutdown has shrunk the pool below its target size,
+            // respawn workers back up to that size now that there's work
+            // for them to do.
+            while self.active_workers.load(Ordering::SeqCst) < self.target_size {
+                let claimed = self
+                    .active_workers
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |cur| {
+                        if cur < self.target_size {
+                            Some(cur + 1)
+                        } else {
+                            None
+                        }
+                    })
+                    .is_ok();
+                if !claimed {
+                    break;
+                }
+
+                let mut workers = self.workers.lock().expect("workers lock poisoned");
+                let id = workers.len();
+                workers.push(Worker::new(
+                    id,
+                    self.receiver.clone(),
+                    Arc::clone(&self.shutdown_flag),
+                    Arc::clone(&self.idle_ticks),
+                    Arc::clone(&self.queued),
+                    Arc::clone(&self.active_workers),
+                    Arc::clone(&self.idle_timeout),
+                ));
+            }
+
+            Ok(())
+        } else {
+            Err(JobRejected)
         }
     }
 
@@ -75,16 +351,24 @@ impl ThreadPool {
     /// Sends a shutdown message to each worker and waits for all
     /// worker threads to exit.
     pub fn shutdown(&mut self) {
+        // Set the shared shutdown flag so workers exit on their next
+        // housekeeping wakeup even if a `Shutdown` message goes missing
+        // (e.g. a miscount after a dynamic `increase`), rather than relying
+        // solely on sending exactly one `Shutdown` per worker.
+        self.shutdown_flag.store(true, Ordering::SeqCst);
+
+        let mut workers = self.workers.lock().expect("workers lock poisoned");
+
         // Stop accepting new jobs
         if let Some(sender) = self.sender.take() {
             // Send a shutdown message to each worker
-            for _ in &self.workers {
+            for _ in workers.iter() {
                 let _ = sender.send(Message::Shutdown);
             }
         }
 
         // Join all worker threads
-        for worker in &mut self.workers {
+        for worker in workers.iter_mut() {
             if let Some(thread) = worker.thread.take() {
                 // Wait for the worker thread to finish
                 let _ = thread.join();
@@ -92,9 +376,37 @@ impl ThreadPool {
         }
     }
 
+    /// A softer shutdown than `shutdown`: drops the job sender so
+    /// subsequent `execute` calls are rejected (return `Err(JobRejected)`),
+    /// and sets `shutdown_flag` as a backstop, but leaves worker threads
+    /// running to drain whatever jobs are already queued first (queued jobs
+    /// are always delivered before a worker notices the flag, since the
+    /// flag is only checked on a `recv_timeout` timeout, not when a job is
+    /// immediately available). Call `join_all` afterward to wait for that
+    /// drain to finish.
+    #[allow(dead_code)]
+    pub fn stop_accepting(&mut self) {
+        self.sender = None;
+        self.shutdown_flag.store(true, Ordering::SeqCst);
+    }
+
+    /// Blocks until every worker thread has exited, without sending an
+    /// explicit shutdown signal. Intended to follow `stop_accepting`, once
+    /// queued jobs have had a chance to run to completion.
+    #[allow(dead_code)]
+    pub fn join_all(&mut self) {
+        let mut workers = self.workers.lock().expect("workers lock poisoned");
+        for worker in workers.iter_mut() {
+            if let Some(thread) = worker.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
     /// Attempts to increase the number of worker threads dynamically
     ///
     /// NOTE: This functionality is intentionally left unimplemented.
+    #[allow(dead_code)]
     pub fn increase(&mut self, n: usize) {
         if n == 0 {
             return;
@@ -105,8 +417,6 @@ impl ThreadPool {
             return;
         }
 
-        let sender = self.sender.as_ref().unwrap();
-
         unimplemented!(
             "Dynamic increase is left as an exercise. Create the pool with required size."
         );
@@ -116,6 +426,7 @@ impl ThreadPool {
 /// Represents a single worker thread in the pool
 struct Worker {
     /// Worker identifier (useful for debugging)
+    #[allow(dead_code)]
     id: usize,
 
     /// Handle to the worker thread
@@ -125,30 +436,80 @@ struct Worker {
 impl Worker {
     /// Creates a new worker thread
     ///
-    /// Each worker waits for messages and executes jobs until it
-    /// receives a shutdown signal.
-    fn new(id: usize, receiver: Arc<Mutex<mpsc::Receiver<Message>>>) -> Worker {
-        let thread = thread::spawn(move || loop {
-            // Block until a message is received
-            let message = {
-                let lock = receiver.lock().expect("Receiver lock poisoned");
-                lock.recv()
-            };
-
-            match message {
-                // Execute a submitted job
-                Ok(Message::NewJob(job)) => {
-                    job();
-                }
+    /// Each worker waits for messages, waking up at least every
+    /// `HOUSEKEEPING_INTERVAL` to check `shutdown_flag` even if no job has
+    /// arrived, and executes jobs until it receives a shutdown signal, the
+    /// channel disconnects, or `shutdown_flag` is set.
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        id: usize,
+        receiver: JobReceiver,
+        shutdown_flag: Arc<AtomicBool>,
+        idle_ticks: Arc<AtomicUsize>,
+        queued: Arc<AtomicUsize>,
+        active_workers: Arc<AtomicUsize>,
+        idle_timeout: Arc<Mutex<Option<Duration>>>,
+    ) -> Worker {
+        tracing::debug!(worker_id = id, "worker spawned");
+        let thread = thread::spawn(move || {
+            let mut last_active = Instant::now();
 
-                // Shutdown signal received
-                Ok(Message::Shutdown) => {
-                    break;
-                }
+            loop {
+                // Block until a message is received or the housekeeping
+                // interval elapses, whichever comes first.
+                match recv_job(&receiver) {
+                    // Execute a submitted job
+                    RecvOutcome::Job(Message::NewJob(job)) => {
+                        queued.fetch_sub(1, Ordering::Relaxed);
+                        last_active = Instant::now();
+                        job();
+                    }
 
-                // Channel disconnected
-                Err(_) => {
-                    break;
+                    // Shutdown signal received
+                    RecvOutcome::Job(Message::Shutdown) => {
+                        tracing::debug!(worker_id = id, reason = "shutdown", "worker exit");
+                        break;
+                    }
+
+                    // No job arrived within the housekeeping interval: check
+                    // the out-of-band shutdown flag and record idle time
+                    // before blocking again.
+                    RecvOutcome::Timeout => {
+                        idle_ticks.fetch_add(1, Ordering::Relaxed);
+                        if shutdown_flag.load(Ordering::Relaxed) {
+                            tracing::debug!(worker_id = id, reason = "shutdown_flag", "worker exit");
+                            break;
+                        }
+
+                        // If an idle timeout is configured and this worker
+                        // has sat idle past it, exit voluntarily to shrink
+                        // the pool, unless doing so would leave no workers
+                        // at all.
+                        let timeout = *idle_timeout.lock().expect("idle_timeout lock poisoned");
+                        if let Some(timeout) = timeout
+                            && last_active.elapsed() >= timeout
+                        {
+                            let shrunk = active_workers
+                                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |cur| {
+                                    if cur > 1 {
+                                        Some(cur - 1)
+                                    } else {
+                                        None
+                                    }
+                                })
+                                .is_ok();
+                            if shrunk {
+                                tracing::debug!(worker_id = id, reason = "idle_timeout", "worker exit");
+                                break;
+                            }
+                        }
+                    }
+
+                    // Channel disconnected
+                    RecvOutcome::Disconnected => {
+                        tracing::debug!(worker_id = id, reason = "disconnected", "worker exit");
+                        break;
+                    }
                 }
             }
         });
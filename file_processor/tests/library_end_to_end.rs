@@ -0,0 +1,42 @@
+// Integration test exercising file_processor as a library dependency,
+// independent of the CLI binary in main.rs: builds a `FileProcessor`
+// directly from the public `processor`/`thread_pool` modules and drives
+// it over a small directory of real files.
+
+use file_processor::processor::FileProcessor;
+use file_processor::thread_pool::ThreadPool;
+use std::fs::File;
+use std::io::Write;
+
+#[test]
+fn drives_file_processor_end_to_end_as_a_library() {
+    let dir = std::env::temp_dir().join("pff_integration_test_dir");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let mut a = File::create(dir.join("a.txt")).unwrap();
+    writeln!(a, "one two three").unwrap();
+    drop(a);
+
+    let mut b = File::create(dir.join("b.txt")).unwrap();
+    writeln!(b, "four five").unwrap();
+    drop(b);
+
+    let progress_path = std::env::temp_dir()
+        .join("pff_integration_test_progress.json")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let pool = ThreadPool::new(2);
+    let processor = FileProcessor::new(pool, progress_path);
+
+    let results = processor.process_dirs_collect(vec![dir.to_str().unwrap().to_string()]);
+    let analyses: Vec<_> = results.into_iter().filter_map(|r| r.ok()).collect();
+
+    assert_eq!(analyses.len(), 2);
+    let total_words: usize = analyses.iter().map(|a| a.stats.word_count).sum();
+    assert_eq!(total_words, 5);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}